@@ -22,6 +22,16 @@ pub trait Layer {
     /// Updates the layer output values
     fn feedforward(&mut self, prev_output: &Self::InputType);
 
+    /// Returns the outputs for a whole batch of inputs. The cached output is left unspecified,
+    /// so `feedforward` has to be called again before `backpropagate`.
+    /// The default evaluates the inputs one after another
+    fn feedforward_batch(&mut self, prev_outputs: &[Self::InputType]) -> Vec<Self::OutputType> where Self::OutputType: Clone {
+        return Vec::from_iter(prev_outputs.iter().map(|prev_output| {
+            self.feedforward(prev_output);
+            self.get_output().clone()
+        }));
+    }
+
     /// Updates the layer deltas values
     fn backpropagate(&mut self, next_deltas : &Self::OutputType);
 
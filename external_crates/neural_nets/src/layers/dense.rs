@@ -108,6 +108,27 @@ impl<const INPUT_SIZE:usize, const OUTPUT_SIZE:usize> Layer for Dense<INPUT_SIZE
         }
     }
 
+    fn feedforward_batch(&mut self, prev_outputs: &[[f32; INPUT_SIZE]]) -> Vec<[f32; OUTPUT_SIZE]> {
+        // every row of weights is read once for the whole batch,
+        // the sums are built in the same order as in `feedforward`
+        let mut outputs = vec![[0.; OUTPUT_SIZE]; prev_outputs.len()];
+        for (j, row) in self.weights.iter().enumerate() {
+            for (output, prev_output) in outputs.iter_mut().zip(prev_outputs) {
+                for (value, weight) in output.iter_mut().zip(row) {
+                    *value += weight * prev_output[j];
+                }
+            }
+        }
+
+        //bias
+        for output in outputs.iter_mut() {
+            for (value, bias) in output.iter_mut().zip(self.bias.iter()) {
+                *value += bias;
+            }
+        }
+        return outputs;
+    }
+
     fn backpropagate(&mut self, next_deltas : &[f32; OUTPUT_SIZE]) {
         // d(i) = W.T(i) * d(i+1)
         // simple matrix multiplication
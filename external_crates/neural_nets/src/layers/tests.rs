@@ -217,4 +217,19 @@ fn test_dense2() {
 
         dense.update_weights(0.5, 0., 0.);
     };
-}
\ No newline at end of file
+}
+#[test]
+fn test_dense_feedforward_batch() {
+    let mut dense = Dense::<3,2>::new();
+    let x = [[1., -2., 0.5], [0., 0., 0.], [-0.25, 3., 1.]];
+
+    let outputs = dense.feedforward_batch(&x);
+    assert_eq!(outputs.len(), 3);
+    for (input, output) in x.iter().zip(outputs.iter()) {
+        dense.feedforward(input);
+        assert_eq!(dense.get_output(), output);
+    }
+
+    // an empty batch gives no outputs
+    assert_eq!(dense.feedforward_batch(&[]), Vec::<[f32;2]>::new());
+}
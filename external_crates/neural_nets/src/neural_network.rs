@@ -63,6 +63,14 @@ macro_rules! create_nn {
                 return self.get_output();
             }
 
+            fn feedforward_batch(&mut self, inputs : &[Self::InputType]) -> Vec<Self::OutputType> {
+                let outputs = self.$head1.feedforward_batch(inputs);
+                $(
+                    let outputs = self.$forward.feedforward_batch(&outputs);
+                )+
+                return outputs;
+            }
+
             fn backpropagate(&mut self, groundtruth : &Self::OutputType) {
                 self.output_deltas = Self::Loss::get_gradient(self.get_output(), groundtruth);
 
@@ -156,6 +164,10 @@ pub trait NeuralNetwork {
 
     fn new() -> Self;
     fn feedforward(&mut self, input : &Self::InputType) -> &Self::OutputType;
+    // Evaluates a batch of inputs layer by layer, returns one output per input.
+    // `Dense` layers go through their weights once for the whole batch instead of once per input.
+    // Afterwards `get_output` is unspecified, call `feedforward` before `backpropagate`.
+    fn feedforward_batch(&mut self, inputs : &[Self::InputType]) -> Vec<Self::OutputType>;
     fn get_output(&self) -> &Self::OutputType;
     fn backpropagate(&mut self, groundtruth : &Self::OutputType);
     fn update_gradient(&mut self, batch_size: f32, momentum: f32);
@@ -275,6 +287,21 @@ fn test_nn() {
 }
 
 
+#[test]
+fn test_nn_feedforward_batch() {
+    let mut nn = MyNN4::new();
+
+    let inputs = [[-1., -1.],[-1., 1.],[1.,-1.],[1.,1.],[0.5,0.25]];
+    let outputs = nn.feedforward_batch(&inputs);
+    assert_eq!(outputs.len(), inputs.len());
+
+    for (input, output) in inputs.iter().zip(outputs.iter()) {
+        assert_eq!(nn.feedforward(input), output);
+    }
+
+    assert_eq!(nn.feedforward_batch(&[]), Vec::<[f32;1]>::new());
+}
+
 create_nn!(
     MyNN5,
     [dense1:Dense<2,2>, relu1:LeakyRelu1D<2>, dense2:Dense<2,2>, relu2:Softmax<2>],
@@ -0,0 +1,360 @@
+use std::fmt;
+use std::io::BufRead;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use neural_nets::NeuralNetwork;
+
+use super::mcts::{Node, GeneralGame, SearchNode, GameError, Evaluator, UPPER_BOUND_CONSTANT};
+use super::negamax::Negamax;
+
+// Something that picks moves in a game: a person, a search or a network.
+// `choose_move` is only called for positions that are not finished.
+pub trait Agent<T:GeneralGame> {
+    fn choose_move(&mut self, game: &T) -> T::Move;
+
+    // called after another player played `index` in the position `game`
+    fn opponent_moved(&mut self, _game: &T, _index: &T::Move) {}
+}
+
+// Reads moves from stdin until a legal one is entered
+pub struct HumanAgent<T:GeneralGame> {
+    pub prompt: String,
+    pub parse: fn(&str) -> Option<T::Move>
+}
+
+impl<T:GeneralGame> Agent<T> for HumanAgent<T> {
+    fn choose_move(&mut self, game: &T) -> T::Move {
+        let stdin = std::io::stdin();
+        loop {
+            println!("{}", self.prompt);
+            let mut line = String::new();
+            stdin.lock().read_line(&mut line).unwrap();
+
+            if let Some(index) = (self.parse)(line.trim()) {
+                match game.clone().try_update(index.clone()) {
+                    Ok(()) => return index,
+                    Err(err) => println!("{}", err)
+                }
+            }
+        }
+    }
+}
+
+pub struct RandomAgent<R:Rng> {
+    pub rng: R
+}
+
+impl<T:GeneralGame, R:Rng> Agent<T> for RandomAgent<R> {
+    fn choose_move(&mut self, game: &T) -> T::Move {
+        return game.get_available().choose(&mut self.rng).unwrap().clone();
+    }
+}
+
+// Flat Monte Carlo: the same number of random playouts after every move, no tree
+pub struct FlatMonteCarloAgent<R:Rng> {
+    pub playouts: usize,
+    pub rng: R
+}
+
+impl<T:GeneralGame, R:Rng> Agent<T> for FlatMonteCarloAgent<R> {
+    fn choose_move(&mut self, game: &T) -> T::Move {
+        let player = game.current_player();
+        let mut best = (i64::MIN, None);
+
+        for index in game.get_available() {
+            let mut node = Node::new(game.clone(), None);
+            node.game.update(index.clone());
+
+            let mut score = 0i64;
+            for _ in 0..self.playouts {
+                score += (node.rollout(&mut self.rng) * player) as i64;
+            }
+            if score > best.0 {
+                best = (score, Some(index));
+            }
+        }
+
+        return best.1.unwrap();
+    }
+}
+
+// UCT search, with `reuse_tree` the subtree of the played moves is kept for the next search
+pub struct MctsAgent<T:GeneralGame, R:Rng> {
+    pub iterations: usize,
+    pub rollouts: u64,
+    // the exploration constant of new trees
+    pub exploration: f32,
+    pub reuse_tree: bool,
+    pub rng: R,
+    root: Option<Node<T>>
+}
+
+impl<T:GeneralGame, R:Rng> MctsAgent<T, R> {
+    pub fn new(iterations: usize, rollouts: u64, rng: R) -> MctsAgent<T, R> {
+        return MctsAgent { iterations, rollouts, exploration: UPPER_BOUND_CONSTANT, reuse_tree: false, rng, root: None };
+    }
+
+    // the tree of the last search, after the moves played since
+    pub fn root(&self) -> Option<&Node<T>> {
+        return self.root.as_ref();
+    }
+}
+
+impl<T:GeneralGame + PartialEq, R:Rng> Agent<T> for MctsAgent<T, R> {
+    fn choose_move(&mut self, game: &T) -> T::Move {
+        // the kept tree is out of date if moves were played without telling the agent, e.g. by a `BookAgent`
+        let mut node = match self.root.take() {
+            Some(node) if self.reuse_tree && node.game == *game => node,
+            _ => {
+                let mut node = Node::new(game.clone(), None);
+                node.exploration = self.exploration;
+                node
+            }
+        };
+
+        for _ in 0..self.iterations {
+            node.propagate(self.rollouts, &mut self.rng);
+        }
+        // a single iteration only expands the root
+        if node.children.is_empty() {
+            node.create_children();
+        }
+
+        let best = node.get_most_visited_child().map(|child| child.move_index.clone().unwrap());
+        let index = best.unwrap_or_else(|| node.children[0].move_index.clone().unwrap());
+
+        if self.reuse_tree {
+            self.root = node.children.into_iter().find(|child| child.move_index.as_ref() == Some(&index));
+        }
+        return index;
+    }
+
+    fn opponent_moved(&mut self, _game: &T, index: &T::Move) {
+        if let Some(node) = self.root.take() {
+            self.root = node.children.into_iter().find(|child| child.move_index.as_ref() == Some(index));
+        }
+    }
+}
+
+// Negamax with alpha-beta pruning to a fixed depth
+pub struct AlphaBetaAgent<'a, T:GeneralGame> {
+    pub depth: usize,
+    pub evaluator: Option<&'a dyn Evaluator<T>>
+}
+
+impl<'a, T:GeneralGame> Agent<T> for AlphaBetaAgent<'a, T> {
+    fn choose_move(&mut self, game: &T) -> T::Move {
+        let result = Negamax::new(self.evaluator).search(game, self.depth);
+        return result.best_move().unwrap().clone();
+    }
+}
+
+// Plays the legal move with the highest policy output of the network.
+// `encode` turns a position into the network input, `score` reads the output for one move.
+pub struct PolicyAgent<N, F, G> {
+    pub network: N,
+    pub encode: F,
+    pub score: G
+}
+
+impl<T, N, F, G> Agent<T> for PolicyAgent<N, F, G>
+where
+    T: GeneralGame,
+    N: NeuralNetwork,
+    F: Fn(&T) -> N::InputType,
+    G: Fn(&N::OutputType, &T::Move) -> f32
+{
+    fn choose_move(&mut self, game: &T) -> T::Move {
+        let output = self.network.feedforward(&(self.encode)(game));
+        let available = game.get_available();
+        return available.into_iter().max_by(|a, b| (self.score)(output, a).total_cmp(&(self.score)(output, b))).unwrap();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord<M> {
+    // the score of the final position
+    pub result: i8,
+    pub moves: Vec<M>
+}
+
+impl<M:fmt::Debug> fmt::Display for GameRecord<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} result {}", self.moves, self.result)
+    }
+}
+
+// Plays a game from `start`, `first` plays for player 1 and `second` for player -1.
+// Illegal moves end the game with an error.
+pub fn play_game<T:GeneralGame>(start: &T, first: &mut dyn Agent<T>, second: &mut dyn Agent<T>) -> Result<GameRecord<T::Move>, GameError<T::Move>> {
+    let mut game = start.clone();
+    let mut moves = Vec::new();
+
+    while game.get_score() == 0 && !game.get_available().is_empty() {
+        let player = game.current_player();
+        let index = if player == 1 {first.choose_move(&game)} else {second.choose_move(&game)};
+
+        let before = game.clone();
+        game.try_update(index.clone())?;
+        if player == 1 {second.opponent_moved(&before, &index)} else {first.opponent_moved(&before, &index)};
+        moves.push(index);
+    }
+
+    return Ok(GameRecord { result: game.get_score(), moves });
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+#[cfg(test)]
+use super::connect4::Connect4;
+#[cfg(test)]
+use neural_nets::{create_nn, Layer, Loss, layers::{Dense, Softmax}, losses::CrossEntropy1D};
+
+#[test]
+fn test_agent_play_game(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let mut first = RandomAgent { rng: StdRng::seed_from_u64(0) };
+    let mut second = RandomAgent { rng: StdRng::seed_from_u64(1) };
+
+    for _ in 0..20 {
+        let record = play_game(&empty, &mut first, &mut second).unwrap();
+        assert!(record.moves.len() >= 5 && record.moves.len() <= 9);
+
+        let mut game = empty.clone();
+        for index in record.moves.iter() {
+            game.update(*index);
+        }
+        assert_eq!(game.get_score(), record.result);
+    }
+
+    // perfect play from both sides draws
+    let mut first = AlphaBetaAgent { depth: 9, evaluator: None };
+    let mut second = AlphaBetaAgent { depth: 9, evaluator: None };
+    assert_eq!(play_game(&empty, &mut first, &mut second).unwrap().result, 0);
+}
+
+// always plays the first square, legal or not
+#[cfg(test)]
+struct StubbornAgent;
+#[cfg(test)]
+impl Agent<TicTacToe> for StubbornAgent {
+    fn choose_move(&mut self, _game: &TicTacToe) -> (usize, usize) {
+        return (0, 0);
+    }
+}
+
+#[test]
+fn test_agent_illegal_move(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let mut random = RandomAgent { rng: StdRng::seed_from_u64(0) };
+    let result = play_game(&empty, &mut StubbornAgent, &mut StubbornAgent);
+    assert_eq!(result, Err(GameError::IllegalMove((0, 0))));
+    assert!(play_game(&empty, &mut random, &mut StubbornAgent).is_err());
+}
+
+#[test]
+fn test_agent_search_agents_beat_random(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+
+    let mut flat = FlatMonteCarloAgent { playouts: 50, rng: StdRng::seed_from_u64(0) };
+    let mut mcts = MctsAgent::new(500, 1, StdRng::seed_from_u64(1));
+    let mut alphabeta = AlphaBetaAgent { depth: 9, evaluator: None };
+
+    for agent in [&mut flat as &mut dyn Agent<TicTacToe>, &mut mcts, &mut alphabeta] {
+        let mut random = RandomAgent { rng: StdRng::seed_from_u64(2) };
+        let mut losses = 0;
+        for _ in 0..20 {
+            if play_game(&empty, agent, &mut random).unwrap().result == -1 {
+                losses += 1;
+            }
+            if play_game(&empty, &mut random, agent).unwrap().result == 1 {
+                losses += 1;
+            }
+        }
+        assert!(losses <= 2);
+    }
+
+    // O to move, only (0,0) wins immediately
+    let tictactoe = TicTacToe::from_string(".XX\nO..\nOX.").unwrap();
+    assert_eq!(FlatMonteCarloAgent { playouts: 50, rng: StdRng::seed_from_u64(0) }.choose_move(&tictactoe), (0, 0));
+    assert_eq!(MctsAgent::new(300, 1, StdRng::seed_from_u64(0)).choose_move(&tictactoe), (0, 0));
+    assert_eq!(AlphaBetaAgent { depth: 9, evaluator: None }.choose_move(&tictactoe), (0, 0));
+}
+
+#[test]
+fn test_agent_mcts_reuse_tree(){
+    let mut mcts = MctsAgent::new(200, 1, StdRng::seed_from_u64(0));
+    mcts.reuse_tree = true;
+    let mut random = RandomAgent { rng: StdRng::seed_from_u64(1) };
+
+    let mut game = Connect4::empty();
+    let index = mcts.choose_move(&game);
+    game.update(index);
+    let kept = mcts.root().unwrap().visits;
+    assert!(kept > 0);
+    assert_eq!(mcts.root().unwrap().game, game);
+
+    let reply = random.choose_move(&game);
+    mcts.opponent_moved(&game, &reply);
+    game.update(reply);
+    assert_eq!(mcts.root().unwrap().game, game);
+    let before = mcts.root().unwrap().visits;
+
+    // the next search continues on the kept subtree
+    let index = mcts.choose_move(&game);
+    game.update(index);
+    assert!(mcts.root().unwrap().visits < before + 200);
+    assert_eq!(mcts.root().unwrap().game, game);
+
+    // a position the kept tree does not belong to is searched from scratch
+    let mut other = Connect4::empty();
+    other.update(0);
+    other.update(0);
+    let index = mcts.choose_move(&other);
+    other.update(index);
+    assert_eq!(mcts.root().unwrap().game, other);
+    assert!(mcts.root().unwrap().visits < 200);
+
+    let record = play_game(&Connect4::empty(), &mut mcts, &mut random).unwrap();
+    assert!(record.moves.len() <= 36);
+}
+
+#[cfg(test)]
+create_nn!(
+    PolicyNet,
+    [dense1: Dense<36,6>, act1: Softmax<6>],
+    CrossEntropy1D<6>
+);
+
+#[cfg(test)]
+fn encode_connect4(game: &Connect4) -> [f32;36] {
+    let mut input = [0f32; 36];
+    for (i, cell) in input.iter_mut().enumerate() {
+        *cell = (game.board[i/6][i%6] * game.current_player()) as f32;
+    }
+    return input;
+}
+
+#[test]
+fn test_agent_policy(){
+    let mut agent = PolicyAgent { network: PolicyNet::new(), encode: encode_connect4, score: |output: &[f32;6], col: &usize| output[*col] };
+
+    let game = Connect4::empty();
+    let expected = *agent.network.feedforward(&encode_connect4(&game));
+    let index = agent.choose_move(&game);
+    assert!(expected.iter().all(|&p| p <= expected[index]));
+
+    // full columns are never chosen
+    let game = Connect4::from_string("X.X.X.\nO.O.O.\nX.X.X.\nO.O.O.\nX.X.X.\nO.O.O.\n").unwrap();
+    for _ in 0..10 {
+        assert_eq!(agent.choose_move(&game) % 2, 1);
+    }
+
+    let mut random = RandomAgent { rng: StdRng::seed_from_u64(0) };
+    assert!(play_game(&Connect4::empty(), &mut agent, &mut random).is_ok());
+}
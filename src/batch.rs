@@ -0,0 +1,238 @@
+use rand::Rng;
+
+use neural_nets::NeuralNetwork;
+
+use super::mcts::{Node, GeneralGame, SearchNode, Evaluator, sample_result};
+
+// Evaluates many positions at once, returns the win probability of the player to move for each
+pub trait BatchEvaluator<T:GeneralGame> {
+    fn evaluate_batch(&mut self, positions: &[T]) -> Vec<f32>;
+}
+
+// Adapter evaluating the positions of a batch one by one
+pub struct PerPosition<'a, T:GeneralGame>(pub &'a dyn Evaluator<T>);
+
+impl<'a, T:GeneralGame> BatchEvaluator<T> for PerPosition<'a, T> {
+    fn evaluate_batch(&mut self, positions: &[T]) -> Vec<f32> {
+        return Vec::from_iter(positions.iter().map(|game| self.0.evaluate(game)));
+    }
+}
+
+// Runs a whole batch through the network with `feedforward_batch`.
+// `encode` turns a position into the network input,
+// `decode` turns the network output into the win probability of the player to move.
+pub struct NetworkEvaluator<N, F, G> {
+    pub network: N,
+    pub encode: F,
+    pub decode: G
+}
+
+impl<T, N, F, G> BatchEvaluator<T> for NetworkEvaluator<N, F, G>
+where
+    T: GeneralGame,
+    N: NeuralNetwork,
+    F: Fn(&T) -> N::InputType,
+    G: Fn(&N::OutputType) -> f32
+{
+    fn evaluate_batch(&mut self, positions: &[T]) -> Vec<f32> {
+        let inputs = Vec::from_iter(positions.iter().map(|game| (self.encode)(game)));
+        let outputs = self.network.feedforward_batch(&inputs);
+        return Vec::from_iter(outputs.iter().map(|output| (self.decode)(output)));
+    }
+}
+
+impl<T:GeneralGame> Node<T> {
+    // Descends to a leaf adding a virtual loss to every node on the way, so that the next
+    // descent of the same batch prefers a different path.
+    // Returns the result if it is known without evaluating the leaf.
+    fn select_leaf<R:Rng>(&mut self, path: &mut Vec<usize>, rng: &mut R) -> Option<i8> {
+        self.visits += 1;
+        self.losses += 1;
+
+        let score = self.game.get_score();
+        if score != 0 {
+            return Some(score);
+        }
+
+        if ! self.created_children() {
+            self.create_children();
+            if self.children.is_empty() {
+                return Some(0);
+            }
+            return None;
+        }
+
+        match self.get_child_with_highest_score(rng) {
+            Some(next) => {
+                path.push(next);
+                return self.children[next].select_leaf(path, rng);
+            }
+            None => return Some(0)
+        }
+    }
+
+    // Replaces the virtual losses along `path` with the real result
+    fn backup(&mut self, path: &[usize], result: i8) {
+        self.losses -= 1;
+
+        let (wins_1, wins_n1) = match result { 1 => (1, 0), -1 => (0, 1), _ => (0, 0) };
+        self.add_result(wins_1, wins_n1);
+
+        if let Some((next, rest)) = path.split_first() {
+            self.children[*next].backup(rest, result);
+        }
+    }
+
+    fn get_leaf(&self, path: &[usize]) -> &Node<T> {
+        let mut node = self;
+        for &index in path {
+            node = &node.children[index];
+        }
+        return node;
+    }
+
+    // Collects up to `batch_size` leaves, evaluates them together and backpropagates all results
+    pub fn propagate_batch<E:BatchEvaluator<T>, R:Rng>(&mut self, batch_size: usize, evaluator: &mut E, rng: &mut R) {
+        let mut paths = Vec::with_capacity(batch_size);
+        let mut results = Vec::with_capacity(batch_size);
+        let mut positions = Vec::new();
+
+        for _ in 0..batch_size {
+            let mut path = Vec::new();
+            let result = self.select_leaf(&mut path, rng);
+            if result.is_none() {
+                let leaf = self.get_leaf(&path);
+                positions.push(leaf.game.clone());
+            }
+            paths.push(path);
+            results.push(result);
+        }
+
+        let mut probabilities = evaluator.evaluate_batch(&positions).into_iter();
+        let mut players = positions.iter().map(|game| game.current_player());
+
+        for (path, result) in paths.iter().zip(results) {
+            let result = match result {
+                Some(result) => result,
+                None => sample_result(probabilities.next().unwrap(), players.next().unwrap(), rng)
+            };
+            self.backup(path, result);
+        }
+    }
+
+    pub fn predict_batch<E:BatchEvaluator<T>>(&mut self, iters: usize, batch_size: usize, evaluator: &mut E) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..iters {
+            self.propagate_batch(batch_size, evaluator, &mut rng);
+        }
+    }
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::mcts::Playout;
+#[cfg(test)]
+use super::connect4::{Connect4, Connect4Heuristic};
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+
+// without draws every visit is either a win or a loss, a leftover virtual loss would break that
+#[cfg(test)]
+fn check_no_virtual_loss<T:GeneralGame>(node: &Node<T>) {
+    assert_eq!(node.wins + node.losses, node.visits);
+    for child in node.children.iter() {
+        check_no_virtual_loss(child);
+    }
+}
+
+#[test]
+fn test_batch_size_one_matches_unbatched(){
+    let heuristic = Connect4Heuristic::default();
+
+    for seed in 0..5u64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut unbatched = Node::new(Connect4::empty(), None);
+        for _ in 0..300 {
+            unbatched.propagate_with(1, &Playout::Evaluate(&heuristic), &mut rng);
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut batched = Node::new(Connect4::empty(), None);
+        let mut evaluator = PerPosition(&heuristic);
+        for _ in 0..300 {
+            batched.propagate_batch(1, &mut evaluator, &mut rng);
+        }
+
+        assert_eq!(batched, unbatched);
+    }
+}
+
+#[test]
+fn test_batch_virtual_loss(){
+    let heuristic = Connect4Heuristic::default();
+    let mut evaluator = PerPosition(&heuristic);
+
+    let mut node = Node::new(Connect4::empty(), None);
+    node.predict_batch(50, 8, &mut evaluator);
+
+    assert_eq!(node.visits, 400);
+    check_no_virtual_loss(&node);
+
+    // the first batch is spread over the children instead of repeating one path
+    let mut node = Node::new(Connect4::empty(), None);
+    node.create_children();
+    node.predict_batch(1, 6, &mut evaluator);
+    assert!(node.children.iter().all(|ch| ch.visits == 1));
+
+    // a won position does not need the evaluator
+    let tictactoe = TicTacToe::from_string("XXX\nOO.\n...").unwrap();
+    let mut node = Node::new(tictactoe, None);
+    let mut evaluator = NetworkEvaluator { network: ValueNet::new(), encode: encode_tictactoe, decode: |_: &[f32;1]| -> f32 { panic!("Evaluated a finished game") } };
+    node.predict_batch(2, 4, &mut evaluator);
+    assert_eq!((node.visits, node.wins, node.losses), (8, 8, 0));
+}
+
+#[cfg(test)]
+use neural_nets::{create_nn, Layer, Loss, layers::{Dense, Sigmoid}, losses::SumSquares1D};
+
+#[cfg(test)]
+create_nn!(
+    ValueNet,
+    [dense1: Dense<9,1>, act1: Sigmoid<1>],
+    SumSquares1D<1>
+);
+
+#[cfg(test)]
+fn encode_tictactoe(game: &TicTacToe) -> [f32;9] {
+    let mut input = [0f32; 9];
+    for (i, cell) in input.iter_mut().enumerate() {
+        *cell = (game.board[i/3][i%3] * game.current_player()) as f32;
+    }
+    return input;
+}
+
+#[test]
+fn test_batch_network_evaluator(){
+    let mut evaluator = NetworkEvaluator { network: ValueNet::new(), encode: encode_tictactoe, decode: |output: &[f32;1]| output[0] };
+
+    let positions = vec![
+        TicTacToe::from_string("...\n...\n...").unwrap(),
+        TicTacToe::from_string("X..\n...\n...").unwrap(),
+        TicTacToe::from_string("X..\n.O.\n..X").unwrap()
+    ];
+    let probabilities = evaluator.evaluate_batch(&positions);
+    assert_eq!(probabilities.len(), 3);
+
+    for (game, probability) in positions.iter().zip(probabilities) {
+        let expected = evaluator.network.feedforward(&encode_tictactoe(game))[0];
+        assert_eq!(probability, expected);
+        assert!(probability > 0. && probability < 1.);
+    }
+
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), None);
+    node.predict_batch(20, 4, &mut evaluator);
+    assert_eq!(node.visits, 80);
+    check_no_virtual_loss(&node);
+}
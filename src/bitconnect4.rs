@@ -0,0 +1,228 @@
+use std::fmt;
+
+use super::mcts::{GeneralGame, Reversible};
+use super::connect4::ConnectN;
+
+// The same game as `ConnectN`, stored as one 64-bit bitboard per player.
+// Bit `col*(H+1) + row` is the cell `row` counted from the bottom, the extra bit on top of
+// every column stays empty, so shifting a line of tokens never wraps into the next column.
+// Boards with (H+1)*W > 64 do not fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitConnectN<const W: usize, const H: usize, const K: usize> {
+    // the tokens of player 1 and of player -1
+    boards: [u64; 2],
+    // tokens in every column
+    heights: [u8; W],
+    // the player to move, flipped by `update` and `undo`
+    player: i8
+}
+
+pub type BitConnect4 = BitConnectN<6, 6, 4>;
+
+impl<const W: usize, const H: usize, const K: usize> BitConnectN<W, H, K> {
+    // shifts to the next cell of a line: vertical, horizontal and both diagonals
+    const SHIFTS : [usize; 4] = [1, H+1, H, H+2];
+
+    pub fn empty() -> BitConnectN<W, H, K> {
+        assert!((H+1)*W <= 64, "A {}x{} board does not fit into 64 bits", W, H);
+        return BitConnectN { boards: [0; 2], heights: [0; W], player: 1 };
+    }
+
+    fn bit(col: usize, row: usize) -> u64 {
+        return 1 << (col*(H+1) + row);
+    }
+
+    fn board_index(player: i8) -> usize {
+        return if player == 1 {0} else {1};
+    }
+
+    // whether the tokens contain K in a row
+    fn has_line(board: u64) -> bool {
+        for shift in Self::SHIFTS {
+            let mut line = board;
+            for k in 1..K {
+                line &= board >> (k*shift);
+            }
+            if line != 0 {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    pub fn from_array(connect4: &ConnectN<W, H, K>) -> BitConnectN<W, H, K> {
+        let mut bitboard = BitConnectN::empty();
+        for col in 0..W {
+            for row in 0..H {
+                let cell = connect4.board[H-1-row][col];
+                if cell != 0 {
+                    bitboard.boards[Self::board_index(cell)] |= Self::bit(col, row);
+                    bitboard.heights[col] += 1;
+                }
+            }
+        }
+        bitboard.player = connect4.current_player();
+        return bitboard;
+    }
+
+    pub fn to_array(&self) -> ConnectN<W, H, K> {
+        let mut board = [[0; W]; H];
+        for (col, &height) in self.heights.iter().enumerate() {
+            for row in 0..height as usize {
+                board[H-1-row][col] = if self.boards[0] & Self::bit(col, row) != 0 {1} else {-1};
+            }
+        }
+        return ConnectN::from_board(board);
+    }
+
+    pub fn from_string(val: &str) -> Option<BitConnectN<W, H, K>> {
+        return ConnectN::from_string(val).map(|connect4| Self::from_array(&connect4));
+    }
+}
+
+impl<const W: usize, const H: usize, const K: usize> GeneralGame for BitConnectN<W, H, K> {
+    // the column to drop the token into
+    type Move = usize;
+
+    fn get_score(&self) -> i8 {
+        if Self::has_line(self.boards[0]) {
+            return 1;
+        }
+        if Self::has_line(self.boards[1]) {
+            return -1;
+        }
+        return 0;
+    }
+
+    fn get_available(&self) -> Vec<usize> {
+        return Vec::from_iter( (0..W).filter(|&col| (self.heights[col] as usize) < H) );
+    }
+
+    fn current_player(&self) -> i8 {
+        return self.player;
+    }
+
+    fn update(&mut self, index:usize) {
+        if self.heights[index] as usize == H {
+            panic!("Out of range.");
+        }
+        self.boards[Self::board_index(self.player)] |= Self::bit(index, self.heights[index] as usize);
+        self.heights[index] += 1;
+        self.player = -self.player;
+    }
+}
+
+impl<const W: usize, const H: usize, const K: usize> Reversible for BitConnectN<W, H, K> {
+    // the column the token was dropped into
+    type Undo = usize;
+
+    fn update_with_undo(&mut self, index:usize) -> usize {
+        self.update(index);
+        return index;
+    }
+
+    fn undo(&mut self, undo: usize) {
+        if self.heights[undo] == 0 {
+            panic!("Empty column.");
+        }
+        self.heights[undo] -= 1;
+        let bit = Self::bit(undo, self.heights[undo] as usize);
+        self.player = if self.boards[0] & bit != 0 {1} else {-1};
+        self.boards[0] &= !bit;
+        self.boards[1] &= !bit;
+    }
+}
+
+impl<const W: usize, const H: usize, const K: usize> fmt::Display for BitConnectN<W, H, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.to_array());
+    }
+}
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+// random games played on both versions side by side
+#[cfg(test)]
+fn cross_validate<const W: usize, const H: usize, const K: usize>(games: usize, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut wins = [0usize; 3];
+
+    for _ in 0..games {
+        let mut array = ConnectN::<W, H, K>::empty();
+        let mut bitboard = BitConnectN::<W, H, K>::empty();
+        let mut history = Vec::new();
+
+        loop {
+            assert_eq!(bitboard.to_array(), array);
+            assert_eq!(BitConnectN::from_array(&array), bitboard);
+            assert_eq!(bitboard.get_score(), array.get_score(), "{}", array);
+            assert_eq!(bitboard.get_available(), array.get_available());
+            assert_eq!(bitboard.current_player(), array.current_player());
+            if array.get_score() != 0 || array.get_available().is_empty() {
+                wins[(array.get_score() + 1) as usize] += 1;
+                break;
+            }
+
+            let index = *array.get_available().choose(&mut rng).unwrap();
+            history.push(bitboard.clone());
+            array.update(index);
+            assert_eq!(bitboard.update_with_undo(index), index);
+        }
+
+        while let Some(before) = history.pop() {
+            let index = Vec::from_iter((0..W).filter(|&col| before.heights[col] != bitboard.heights[col]))[0];
+            bitboard.undo(index);
+            assert_eq!(bitboard, before);
+        }
+        assert_eq!(bitboard, BitConnectN::empty());
+    }
+
+    // both players won some games
+    assert!(wins[0] > 0 && wins[2] > 0, "{:?}", wins);
+}
+
+#[test]
+fn test_bitconnect4_cross_validate(){
+    cross_validate::<6, 6, 4>(500, 0);
+    cross_validate::<7, 6, 4>(300, 1);
+    cross_validate::<8, 7, 5>(300, 2);
+    cross_validate::<5, 4, 3>(300, 3);
+}
+
+#[test]
+fn test_bitconnect4_known_positions(){
+    let str = "\
+                        X.....\n\
+                        X.....\n\
+                        O..O..\n\
+                        XO.X..\n\
+                        XOOXX.\n\
+                        XXOOOO\n\
+                    ";
+    let bitboard = BitConnect4::from_string(str).unwrap();
+    assert_eq!(bitboard.get_score(), -1);
+    assert_eq!(bitboard.to_string(), ConnectN::<6, 6, 4>::from_string(str).unwrap().to_string());
+    assert_eq!(bitboard.get_available(), [1, 2, 3, 4, 5]);
+
+    // a diagonal in the top right corner
+    let str = "\
+                        .....X\n\
+                        ....XO\n\
+                        ...XOX\n\
+                        ..XOOO\n\
+                        ..OXXO\n\
+                        ..XOXO\n\
+                    ";
+    let bitboard = BitConnect4::from_string(str).unwrap();
+    assert_eq!(bitboard.get_score(), 1);
+    assert_eq!(bitboard.get_available(), [0, 1, 2, 3, 4]);
+
+    assert_eq!(BitConnect4::from_string("......\n......\n......\n...X..\n......\n...O..\n"), None);
+}
+
+#[test]
+#[should_panic(expected = "does not fit")]
+fn test_bitconnect4_too_large(){
+    BitConnectN::<9, 7, 5>::empty();
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use super::mcts::{Node, GeneralGame, Symmetries};
+use super::connect4::Connect4;
+use super::agent::Agent;
+
+// Book files, plain text:
+//
+//     connect4-book <version>
+//     <position> <column> <visits> <wins> <losses>
+//     ...
+//
+// The header is the first line, every other line is the search statistics of one move.
+// <position> is the WIDTH*HEIGHT cells of the board row by row from the top as X, O or '.', stored in the
+// orientation of its canonical key (see `Symmetries`) so mirrored positions share their moves.
+// <column> is the move in that orientation counting from 0, <wins> and <losses> are counted for the
+// player making the move. Empty lines and lines starting with '#' are ignored.
+// Files of another version are rejected.
+pub const BOOK_VERSION : u32 = 1;
+const BOOK_HEADER : &str = "connect4-book";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookMove {
+    pub column: usize,
+    pub visits: u64,
+    pub wins: u64,
+    pub losses: u64
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OpeningBook {
+    // moves in the canonical orientation of the position
+    entries: HashMap<String, Vec<BookMove>>
+}
+
+fn position_string(connect4: &Connect4) -> String {
+    return String::from_iter(connect4.board.iter().flatten().map(|&cell| if cell == 1 {'X'} else if cell == -1 {'O'} else {'.'}));
+}
+
+fn invalid_data(message: String) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        return OpeningBook::default();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    // the key of the position and the symmetry turning it into the stored orientation
+    fn key(connect4: &Connect4) -> (String, usize) {
+        let (_, symmetry) = connect4.canonical_key();
+        return (position_string(&connect4.transform(symmetry)), symmetry);
+    }
+
+    // `moves` are given in the orientation of `connect4`
+    pub fn insert(&mut self, connect4: &Connect4, moves: Vec<BookMove>) {
+        let (key, symmetry) = Self::key(connect4);
+        let moves = Vec::from_iter(moves.into_iter().map(|book_move| BookMove { column: connect4.transform_move(symmetry, &book_move.column), ..book_move }));
+        self.entries.insert(key, moves);
+    }
+
+    // the stored moves in the orientation of `connect4`
+    pub fn get(&self, connect4: &Connect4) -> Option<Vec<BookMove>> {
+        let (key, symmetry) = Self::key(connect4);
+        let canonical = connect4.transform(symmetry);
+        let inverse = connect4.inverse(symmetry);
+
+        let moves = self.entries.get(&key)?;
+        return Some(Vec::from_iter(moves.iter().map(|book_move| BookMove { column: canonical.transform_move(inverse, &book_move.column), ..book_move.clone() })));
+    }
+
+    // the most visited move of the position
+    pub fn best_move(&self, connect4: &Connect4) -> Option<usize> {
+        let moves = self.get(connect4)?;
+        return moves.iter().max_by_key(|book_move| book_move.visits).map(|book_move| book_move.column);
+    }
+
+    // Searches every position of the first `plies` plies with `iterations` iterations of MCTS
+    pub fn build<R:Rng>(plies: usize, iterations: usize, rollouts: u64, rng: &mut R) -> OpeningBook {
+        let mut book = OpeningBook::new();
+        let mut positions = vec![Connect4::empty()];
+
+        for _ in 0..plies {
+            let mut next = Vec::new();
+            for connect4 in positions {
+                if book.get(&connect4).is_some() || connect4.get_score() != 0 || connect4.get_available().is_empty() {
+                    continue;
+                }
+
+                let mut node = Node::new(connect4.clone(), None);
+                for _ in 0..iterations {
+                    node.propagate(rollouts, rng);
+                }
+                book.insert(&connect4, Vec::from_iter(node.children.iter().map(|child| BookMove {
+                    column: child.move_index.unwrap(), visits: child.visits, wins: child.wins, losses: child.losses
+                })));
+
+                next.extend(node.children.into_iter().map(|child| child.game));
+            }
+            positions = next;
+        }
+
+        return book;
+    }
+
+    pub fn write<W:Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{} {}", BOOK_HEADER, BOOK_VERSION)?;
+
+        // sorted, so that the same book always gives the same file
+        let mut keys = Vec::from_iter(self.entries.keys());
+        keys.sort();
+        for key in keys {
+            for book_move in self.entries[key].iter() {
+                writeln!(writer, "{} {} {} {} {}", key, book_move.column, book_move.visits, book_move.wins, book_move.losses)?;
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn read<R:BufRead>(reader: R) -> io::Result<OpeningBook> {
+        let mut lines = reader.lines();
+
+        let header = lines.next().ok_or_else(|| invalid_data(String::from("Empty book")))??;
+        match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [BOOK_HEADER, version] if version == BOOK_VERSION.to_string() => (),
+            [BOOK_HEADER, version] => return Err(invalid_data(format!("Unsupported book version {}", version))),
+            _ => return Err(invalid_data(format!("Not a book: {}", header)))
+        }
+
+        let mut book = OpeningBook::new();
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || invalid_data(format!("Invalid book entry on line {}: {}", number + 2, line));
+            let fields = Vec::from_iter(line.split_whitespace());
+            if fields.len() != 5 || fields[0].len() != Connect4::WIDTH * Connect4::HEIGHT || ! fields[0].chars().all(|s| s == 'X' || s == 'O' || s == '.') {
+                return Err(error());
+            }
+            let numbers = Vec::from_iter(fields[1..].iter().map(|field| field.parse::<u64>()));
+            if numbers.iter().any(|number| number.is_err()) || *numbers[0].as_ref().unwrap() >= Connect4::WIDTH as u64 {
+                return Err(error());
+            }
+            let numbers = Vec::from_iter(numbers.into_iter().map(|number| number.unwrap()));
+
+            book.entries.entry(String::from(fields[0])).or_default().push(BookMove {
+                column: numbers[0] as usize, visits: numbers[1], wins: numbers[2], losses: numbers[3]
+            });
+        }
+
+        return Ok(book);
+    }
+
+    pub fn save<P:AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        return writer.flush();
+    }
+
+    pub fn load<P:AsRef<Path>>(path: P) -> io::Result<OpeningBook> {
+        return OpeningBook::read(BufReader::new(File::open(path)?));
+    }
+}
+
+// Plays the book move if there is one, otherwise asks `fallback`
+pub struct BookAgent<A:Agent<Connect4>> {
+    pub book: OpeningBook,
+    pub fallback: A
+}
+
+impl<A:Agent<Connect4>> Agent<Connect4> for BookAgent<A> {
+    fn choose_move(&mut self, connect4: &Connect4) -> usize {
+        return match self.book.best_move(connect4) {
+            Some(index) => index,
+            None => self.fallback.choose_move(connect4)
+        };
+    }
+
+    fn opponent_moved(&mut self, connect4: &Connect4, index: &usize) {
+        self.fallback.opponent_moved(connect4, index);
+    }
+}
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::agent::MctsAgent;
+
+#[test]
+fn test_book_build(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let book = OpeningBook::build(2, 200, 1, &mut rng);
+
+    // the empty board and the three first moves up to the mirror image
+    assert_eq!(book.len(), 4);
+
+    let moves = book.get(&Connect4::empty()).unwrap();
+    assert_eq!(Vec::from_iter(moves.iter().map(|book_move| book_move.column)), [0, 1, 2, 3, 4, 5]);
+    // the first iteration only expands the root
+    assert_eq!(moves.iter().map(|book_move| book_move.visits).sum::<u64>(), 199);
+
+    // mirrored positions give mirrored moves
+    let mut left = Connect4::empty();
+    left.update(1);
+    let mut right = Connect4::empty();
+    right.update(4);
+    let best = book.best_move(&left).unwrap();
+    assert_eq!(book.best_move(&right), Some(5 - best));
+
+    let mut deeper = left.clone();
+    deeper.update(0);
+    assert_eq!(book.get(&deeper), None);
+}
+
+#[test]
+fn test_book_read_write(){
+    let mut rng = StdRng::seed_from_u64(1);
+    let book = OpeningBook::build(2, 50, 1, &mut rng);
+
+    let mut file = Vec::new();
+    book.write(&mut file).unwrap();
+    let text = String::from_utf8(file.clone()).unwrap();
+    assert!(text.starts_with("connect4-book 1\n"));
+    assert_eq!(text.lines().count(), 1 + 4*6);
+    assert_eq!(OpeningBook::read(&file[..]).unwrap(), book);
+
+    let mut again = Vec::new();
+    OpeningBook::read(&file[..]).unwrap().write(&mut again).unwrap();
+    assert_eq!(again, file);
+
+    // comments and empty lines
+    let text = "connect4-book 1\n# comment\n\n....................................  2 10 6 3\n";
+    let book = OpeningBook::read(text.as_bytes()).unwrap();
+    assert_eq!(book.get(&Connect4::empty()), Some(vec![BookMove { column: 2, visits: 10, wins: 6, losses: 3 }]));
+
+    assert_eq!(OpeningBook::read("connect4-book 2\n".as_bytes()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(OpeningBook::read("".as_bytes()).is_err());
+    assert!(OpeningBook::read("something else\n".as_bytes()).is_err());
+    assert!(OpeningBook::read("connect4-book 1\n.... 2 10 6 3\n".as_bytes()).is_err());
+    assert!(OpeningBook::read("connect4-book 1\n.................................... 6 10 6 3\n".as_bytes()).is_err());
+    assert!(OpeningBook::read("connect4-book 1\n.................................... 2 10 x 3\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_book_agent(){
+    let mut book = OpeningBook::new();
+    book.insert(&Connect4::empty(), vec![BookMove { column: 1, visits: 10, wins: 5, losses: 5 }, BookMove { column: 3, visits: 20, wins: 10, losses: 10 }]);
+
+    let path = std::env::temp_dir().join(format!("mcts-book-test-{}.book", std::process::id()));
+    book.save(&path).unwrap();
+    let book = OpeningBook::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut agent = BookAgent { book, fallback: MctsAgent::new(100, 1, StdRng::seed_from_u64(0)) };
+    assert_eq!(agent.choose_move(&Connect4::empty()), 3);
+
+    // positions missing from the book are searched
+    let mut connect4 = Connect4::empty();
+    connect4.update(0);
+    assert!(connect4.get_available().contains(&agent.choose_move(&connect4)));
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use super::mcts::GeneralGame;
+
+// Reference rules a game is checked against, computed from scratch,
+// e.g. by a scan of the whole board or a slow move generator
+pub trait Oracle<T:GeneralGame> {
+    fn score(&self, game: &T) -> i8;
+    // the legal moves in any order, only asked while nobody has won
+    fn available(&self, game: &T) -> Vec<T::Move>;
+}
+
+// a pair of closures (score, available)
+impl<T, S, A> Oracle<T> for (S, A)
+where
+    T: GeneralGame,
+    S: Fn(&T) -> i8,
+    A: Fn(&T) -> Vec<T::Move>
+{
+    fn score(&self, game: &T) -> i8 {
+        return (self.0)(game);
+    }
+    fn available(&self, game: &T) -> Vec<T::Move> {
+        return (self.1)(game);
+    }
+}
+
+// Test harness for new games: generic checks every `GeneralGame` implementation should pass.
+// `playouts` random games are played from `start`, no game may be longer than `max_length` plies.
+// The score and the moves of every position have to agree with `oracle`.
+pub fn check_game<T, O, R>(start: &T, oracle: &O, playouts: usize, max_length: usize, rng: &mut R)
+where
+    T: GeneralGame + fmt::Debug + PartialEq,
+    O: Oracle<T>,
+    R: Rng
+{
+    for _ in 0..playouts {
+        let mut game = start.clone();
+        let mut length = 0usize;
+
+        loop {
+            let score = game.get_score();
+            assert!(score == 1 || score == 0 || score == -1, "Invalid score {} in {:?}", score, game);
+            let player = game.current_player();
+            assert!(player == 1 || player == -1, "Invalid player {} in {:?}", player, game);
+
+            assert_eq!(score, oracle.score(&game), "Score disagrees with the oracle in {:?}", game);
+
+            let available = game.get_available();
+            if score != 0 {
+                break;
+            }
+
+            // the same moves as the oracle
+            let expected = oracle.available(&game);
+            for (i, index) in available.iter().enumerate() {
+                assert!(! available[..i].contains(index), "Duplicate move {:?} in {:?}", index, game);
+                assert!(expected.contains(index), "Illegal move {:?} available in {:?}", index, game);
+            }
+            for index in expected.iter() {
+                assert!(available.contains(index), "Legal move {:?} missing in {:?}", index, game);
+            }
+            if available.is_empty() {
+                break;
+            }
+
+            for index in available.iter() {
+                // playing on a clone leaves the original untouched
+                let before = game.clone();
+                let mut child = game.clone();
+                child.update(index.clone());
+                assert_eq!(game, before, "Playing on a clone changed the original");
+                assert!(child != game, "Move {:?} did not change {:?}", index, game);
+            }
+
+            game.update(available.choose(rng).unwrap().clone());
+            length += 1;
+            assert!(length <= max_length, "Game longer than {} plies: {:?}", max_length, game);
+        }
+    }
+}
+
+// `from_string` has to read back what `Display` writes, for positions along random games from `start`
+pub fn check_from_string<T, F, R>(start: &T, from_string: F, playouts: usize, rng: &mut R)
+where
+    T: GeneralGame + fmt::Debug + fmt::Display + PartialEq,
+    F: Fn(&str) -> Option<T>,
+    R: Rng
+{
+    for _ in 0..playouts {
+        let mut game = start.clone();
+
+        loop {
+            assert_eq!(from_string(&format!("{}", game)).as_ref(), Some(&game), "Round trip failed for\n{}", game);
+
+            let available = game.get_available();
+            if game.get_score() != 0 || available.is_empty() {
+                break;
+            }
+            game.update(available.choose(rng).unwrap().clone());
+        }
+    }
+}
+
+
+use super::tictactoe::TicTacToe;
+use super::mnk::MNKGame;
+use super::connect4::{Connect4, StandardConnect4, Connect5, ConnectN};
+use super::bitconnect4::BitConnect4;
+use super::othello::{Othello, OthelloMove};
+use super::hex::{Hex, HexMove, flood_fill_winner};
+use super::mcts::IndexedGame;
+
+// the player with K in a row, by trying every line of K cells
+fn scan_lines<const M: usize, const N: usize>(board: &[[i8; N]; M], k: usize) -> i8 {
+    let mut winners = Vec::new();
+    for (drow, dcol) in [(0isize, 1isize), (1, 0), (1, 1), (1, -1)] {
+        for row in 0..M as isize {
+            for col in 0..N as isize {
+                let cells = Vec::from_iter((0..k as isize).map(|i| (row + i*drow, col + i*dcol)));
+                if cells.iter().all(|&(r, c)| r >= 0 && r < M as isize && c >= 0 && c < N as isize) {
+                    let first = board[row as usize][col as usize];
+                    if first != 0 && cells.iter().all(|&(r, c)| board[r as usize][c as usize] == first) {
+                        winners.push(first);
+                    }
+                }
+            }
+        }
+    }
+    // games end with the first line, so nobody else can have one
+    winners.dedup();
+    assert!(winners.len() <= 1, "Both players have a line");
+    return winners.first().cloned().unwrap_or(0);
+}
+
+fn empty_cells<const M: usize, const N: usize>(board: &[[i8; N]; M]) -> Vec<(usize, usize)> {
+    return Vec::from_iter((0..M*N).map(|cell| (cell / N, cell % N)).filter(|&(row, col)| board[row][col] == 0));
+}
+
+fn connect_n_oracle<const W: usize, const H: usize, const K: usize>() -> (impl Fn(&ConnectN<W, H, K>) -> i8, impl Fn(&ConnectN<W, H, K>) -> Vec<usize>) {
+    return (|game: &ConnectN<W, H, K>| scan_lines(&game.board, K), |game: &ConnectN<W, H, K>| Vec::from_iter((0..W).filter(|&col| game.board[0][col] == 0)));
+}
+
+#[test]
+fn test_conformance_tictactoe(){
+    let mut rng = rand::thread_rng();
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let oracle = (|game: &TicTacToe| scan_lines(&game.board, 3), |game: &TicTacToe| empty_cells(&game.board));
+    check_game(&empty, &oracle, 200, 9, &mut rng);
+    check_from_string(&empty, TicTacToe::from_string, 50, &mut rng);
+}
+
+#[test]
+fn test_conformance_mnk(){
+    let mut rng = rand::thread_rng();
+    let oracle = (|game: &MNKGame<15, 15, 5>| scan_lines(&game.board, 5), |game: &MNKGame<15, 15, 5>| empty_cells(&game.board));
+    check_game(&MNKGame::<15, 15, 5>::empty(), &oracle, 20, 225, &mut rng);
+    let oracle = (|game: &MNKGame<4, 6, 3>| scan_lines(&game.board, 3), |game: &MNKGame<4, 6, 3>| empty_cells(&game.board));
+    check_game(&MNKGame::<4, 6, 3>::empty(), &oracle, 100, 24, &mut rng);
+    check_from_string(&MNKGame::<4, 6, 3>::empty(), MNKGame::<4, 6, 3>::from_string, 50, &mut rng);
+}
+
+#[test]
+fn test_conformance_connect4(){
+    let mut rng = rand::thread_rng();
+    check_game(&Connect4::empty(), &connect_n_oracle(), 100, 36, &mut rng);
+    check_from_string(&Connect4::empty(), Connect4::from_string, 50, &mut rng);
+    check_game(&StandardConnect4::empty(), &connect_n_oracle(), 100, 42, &mut rng);
+    check_game(&Connect5::empty(), &connect_n_oracle(), 50, 63, &mut rng);
+    check_from_string(&Connect5::empty(), Connect5::from_string, 20, &mut rng);
+
+    let (score, available) = connect_n_oracle::<6, 6, 4>();
+    let oracle = (|game: &BitConnect4| score(&game.to_array()), |game: &BitConnect4| available(&game.to_array()));
+    check_game(&BitConnect4::empty(), &oracle, 100, 36, &mut rng);
+    check_from_string(&BitConnect4::empty(), BitConnect4::from_string, 50, &mut rng);
+}
+
+// the cells where `player` encloses a line of opponent discs, found without the game's own move generator
+fn othello_placements<const N: usize>(board: &[[i8; N]; N], player: i8) -> Vec<OthelloMove> {
+    let encloses = |row: usize, col: usize, drow: isize, dcol: isize| {
+        let line = Vec::from_iter((1..N as isize)
+            .map(|k| (row as isize + k*drow, col as isize + k*dcol))
+            .take_while(|&(r, c)| r >= 0 && r < N as isize && c >= 0 && c < N as isize)
+            .map(|(r, c)| board[r as usize][c as usize]));
+        let opponents = line.iter().take_while(|&&cell| cell == -player).count();
+        return opponents > 0 && line.get(opponents) == Some(&player);
+    };
+    let directions = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+    return Vec::from_iter(empty_cells(board).into_iter()
+        .filter(|&(row, col)| directions.iter().any(|&(drow, dcol)| encloses(row, col, drow, dcol)))
+        .map(|(row, col)| OthelloMove::Place(row, col)));
+}
+
+fn othello_oracle<const N: usize>() -> (impl Fn(&Othello<N>) -> i8, impl Fn(&Othello<N>) -> Vec<OthelloMove>) {
+    let score = |game: &Othello<N>| {
+        let player = game.current_player();
+        if ! othello_placements(&game.board, player).is_empty() || ! othello_placements(&game.board, -player).is_empty() {
+            return 0;
+        }
+        return game.board.iter().flatten().map(|&cell| cell as i32).sum::<i32>().signum() as i8;
+    };
+    let available = |game: &Othello<N>| {
+        let player = game.current_player();
+        let placements = othello_placements(&game.board, player);
+        if placements.is_empty() && ! othello_placements(&game.board, -player).is_empty() {
+            return vec![OthelloMove::Pass];
+        }
+        return placements;
+    };
+    return (score, available);
+}
+
+#[test]
+fn test_conformance_othello(){
+    let mut rng = rand::thread_rng();
+    check_game(&Othello::<8>::new(), &othello_oracle(), 50, 128, &mut rng);
+    check_game(&Othello::<6>::new(), &othello_oracle(), 100, 72, &mut rng);
+    check_from_string(&Othello::<6>::new(), Othello::<6>::from_string, 50, &mut rng);
+}
+
+fn hex_oracle<const N: usize>() -> (impl Fn(&Hex<N>) -> i8, impl Fn(&Hex<N>) -> Vec<HexMove>) {
+    let score = |game: &Hex<N>| if flood_fill_winner(&game.board, 1) {1} else if flood_fill_winner(&game.board, -1) {-1} else {0};
+    let available = |game: &Hex<N>| {
+        let mut moves = Vec::from_iter(empty_cells(&game.board).into_iter().map(|(row, col)| HexMove::Place(row, col)));
+        let stones = Vec::from_iter(game.board.iter().flatten().filter(|&&cell| cell != 0));
+        if game.swap_rule && stones == [&1] {
+            moves.push(HexMove::Swap);
+        }
+        return moves;
+    };
+    return (score, available);
+}
+
+#[test]
+fn test_conformance_hex(){
+    let mut rng = rand::thread_rng();
+    check_game(&Hex::<11>::default(), &hex_oracle(), 50, 121, &mut rng);
+    // the swap is one more move
+    check_game(&Hex::<5>::new(true), &hex_oracle(), 200, 26, &mut rng);
+    check_from_string(&Hex::<5>::default(), Hex::<5>::from_string, 100, &mut rng);
+}
+
+// offers taking zero, which does not change the position
+#[derive(Debug, Clone, PartialEq)]
+struct NullMove(usize);
+impl IndexedGame for NullMove {
+    fn update(&mut self, index:usize) {
+        self.0 += index;
+    }
+    fn get_score(&self) -> i8 {
+        return 0;
+    }
+    fn get_available(&self) -> Vec<usize> {
+        return if self.0 < 3 {vec![0, 1]} else {Vec::new()};
+    }
+    fn current_player(&self) -> i8 {
+        return 1;
+    }
+}
+
+#[test]
+#[should_panic(expected = "did not change")]
+fn test_conformance_detects_null_move(){
+    let oracle = (|_: &NullMove| 0, |game: &NullMove| if game.0 < 3 {vec![0, 1]} else {Vec::new()});
+    check_game(&NullMove(0), &oracle, 1, 10, &mut rand::thread_rng());
+}
+
+#[test]
+#[should_panic(expected = "Legal move 2 missing")]
+fn test_conformance_detects_missing_move(){
+    let oracle = (|_: &NullMove| 0, |game: &NullMove| if game.0 < 3 {vec![0, 1, 2]} else {Vec::new()});
+    check_game(&NullMove(0), &oracle, 1, 10, &mut rand::thread_rng());
+}
+
+#[test]
+#[should_panic(expected = "Score disagrees")]
+fn test_conformance_detects_wrong_score(){
+    // the oracle says that player 1 has won, the game does not
+    let oracle = (|_: &NullMove| 1, |_: &NullMove| vec![0, 1]);
+    check_game(&NullMove(0), &oracle, 1, 10, &mut rand::thread_rng());
+}
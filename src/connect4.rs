@@ -1,39 +1,59 @@
 use std::fmt;
 
-use super::mcts::GeneralGame;
+use super::mcts::{GeneralGame, Evaluator, Reversible, Symmetries};
+use super::mnk::{DIRECTIONS, completes_line};
 
+// Tokens are dropped into one of W columns and fall to the lowest free of H rows,
+// whoever first gets K in a row horizontally, vertically or diagonally wins.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Connect4{
-    pub board: [[i8;6];6]
+pub struct ConnectN<const W: usize, const H: usize, const K: usize> {
+    pub board: [[i8;W];H],
+    // kept up to date by `update` from the lines through the new token, so `get_score` does not scan the board
+    score: i8,
+    // the player to move, flipped by `update` and `undo`
+    player: i8
 }
 
-impl Connect4 {
-    pub fn from_string(val : &str) -> Option<Connect4> {
-        let mut connect4 = Connect4 {board:[[0;6];6]};
+// The board all the bots, the solver and the opening book use
+pub type Connect4 = ConnectN<6, 6, 4>;
+// The board sold in shops
+pub type StandardConnect4 = ConnectN<7, 6, 4>;
+pub type Connect5 = ConnectN<9, 7, 5>;
 
-        for (i,s) in val.chars().enumerate(){
-            if (i+1)%7==0 {
+impl<const W: usize, const H: usize, const K: usize> ConnectN<W, H, K> {
+    pub const WIDTH : usize = W;
+    pub const HEIGHT : usize = H;
+
+    pub fn from_string(val : &str) -> Option<ConnectN<W, H, K>> {
+        let mut connect4 = ConnectN::<W, H, K>::empty();
+
+        // spaces are ignored, so the output of `Display` can be read back
+        for (i,s) in val.chars().filter(|&s| s != ' ').enumerate(){
+            if (i+1)%(W+1)==0 {
                 if s != '\n' && s != '\r'{
                     return None;
                 }
             }
             else {
+                let x = i%(W+1);
+                let y = i/(W+1);
+                if y >= H {
+                    return None;
+                }
                 if s != '.'{
                     let target = match s {
                         'X' => 1i8,
                         'O' => -1i8,
                         _ => return None
                     };
-                    let x = i%7;
-                    let y = i/7;
                     connect4.board[y][x] = target;
                 }
             }
         }
         // check for gaps
-        for col in 0..6usize{
+        for col in 0..W {
             let mut gap = false;
-            for row in (0..6usize).rev() {
+            for row in (0..H).rev() {
                 if connect4.board[row][col] == 0{
                     gap = true;
                 }
@@ -43,118 +63,216 @@ impl Connect4 {
             }
         }
 
-        return Some(connect4)
+        return Some(ConnectN::from_board(connect4.board))
     }
 
-    pub fn empty() -> Connect4 {
-        return Connect4 { board: [[0;6];6] };
+    pub fn empty() -> ConnectN<W, H, K> {
+        return ConnectN { board: [[0;W];H], score: 0, player: 1 };
     }
-}
 
-impl fmt::Display for Connect4 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..6usize{
-            for j in 0..6usize{
-                write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
+    pub fn from_board(board: [[i8;W];H]) -> ConnectN<W, H, K> {
+        // X always starts, so the player to move follows from the number of tokens
+        let count_1 = board.iter().flatten().filter(|&&cell| cell == 1).count();
+        let count_n1 = board.iter().flatten().filter(|&&cell| cell == -1).count();
+        let player = if count_1 > count_n1 {-1} else {1};
+
+        let mut connect4 = ConnectN { board, score: 0, player };
+        connect4.score = connect4.scan_score();
+        return connect4;
+    }
+
+    // the winner from a scan of every line of K cells, by the direction and its first cell
+    pub fn scan_score(&self) -> i8 {
+        for (drow, dcol) in DIRECTIONS {
+            for row in 0..H {
+                for col in 0..W {
+                    let player = self.board[row][col];
+                    if player == 0 {
+                        continue;
+                    }
+
+                    let (end_row, end_col) = (row as isize + (K as isize - 1)*drow, col as isize + (K as isize - 1)*dcol);
+                    if end_row >= H as isize || end_col < 0 || end_col >= W as isize {
+                        continue;
+                    }
+
+                    if (1..K as isize).all(|k| self.board[(row as isize + k*drow) as usize][(col as isize + k*dcol) as usize] == player) {
+                        return player;
+                    }
+                }
             }
-            write!(f, "\n").unwrap();
         }
-        write!(f, "")
+
+        return 0;
     }
 }
 
-impl GeneralGame for Connect4 {
-    fn get_score(&self) -> i8 {
-        let mut count_1 : u8;
-        let mut count_n1 : u8;
-
-        // Rows
-        for row in 0..6usize {
-            count_1 = 0;
-            count_n1 = 0;
-            for col in 0..6usize {
-                if self.board[row][col] == -1 { count_n1 += 1; }
-                else {count_n1 = 0; }
-
-                if self.board[row][col] == 1 { count_1 += 1; }
-                else {count_1 = 0; }
-
-                if count_1 >= 4 {return 1;}
-                if count_n1 >= 4 {return -1;}
+// Symmetry 1 mirrors the board horizontally
+impl<const W: usize, const H: usize, const K: usize> Symmetries for ConnectN<W, H, K> {
+    type Key = [[i8;W];H];
+
+    fn symmetry_count(&self) -> usize {
+        return 2;
+    }
+
+    fn transform(&self, symmetry: usize) -> ConnectN<W, H, K> {
+        let mut transformed = self.clone();
+        if symmetry == 1 {
+            for row in transformed.board.iter_mut() {
+                row.reverse();
             }
         }
+        return transformed;
+    }
 
-        // Columns
-        for col in 0..6usize {
-            count_1 = 0;
-            count_n1 = 0;
-            for row in 0..6usize {
-                if self.board[row][col] == -1 { count_n1 += 1; }
-                else {count_n1 = 0; }
+    fn transform_move(&self, symmetry: usize, index: &usize) -> usize {
+        return if symmetry == 1 {W - 1 - index} else {*index};
+    }
 
-                if self.board[row][col] == 1 { count_1 += 1; }
-                else {count_1 = 0; }
+    fn inverse(&self, symmetry: usize) -> usize {
+        return symmetry;
+    }
 
-                if count_1 >= 4 {return 1;}
-                if count_n1 >= 4 {return -1;}
+    fn key(&self) -> [[i8;W];H] {
+        return self.board;
+    }
+}
+
+impl<const W: usize, const H: usize, const K: usize> fmt::Display for ConnectN<W, H, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..H {
+            for j in 0..W {
+                write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
             }
+            writeln!(f).unwrap();
         }
+        write!(f, "")
+    }
+}
 
-        // Left diagonals
-        for offset in (1-6)..6 {
-            count_1 = 0;
-            count_n1 = 0;
-            for row in (-offset).max(0)..(6-offset).min(6) {
-                let col = offset + row;
+impl<const W: usize, const H: usize, const K: usize> GeneralGame for ConnectN<W, H, K> {
+    // the column to drop the token into
+    type Move = usize;
 
-                if self.board[row as usize][col as usize] == -1 { count_n1 += 1; }
-                else {count_n1 = 0; }
+    fn get_score(&self) -> i8 {
+        return self.score;
+    }
 
-                if self.board[row as usize][col as usize] == 1 { count_1 += 1; }
-                else {count_1 = 0; }
+    fn get_available(&self) -> Vec<usize> {
+        return Vec::from_iter( (0..W).filter(|&col| self.board[0][col] == 0) );
+    }
+
+    fn current_player(&self) -> i8 {
+        return self.player;
+    }
 
-                if count_1 >= 4 {return 1;}
-                if count_n1 >= 4 {return -1;}
+    fn update(&mut self, index:usize) {
+        let player = self.player;
+        for row in (0..H).rev() {
+            if self.board[row][index] == 0 {
+                self.board[row][index] = player;
+                // only lines through the new token can be new
+                if self.score == 0 && completes_line(&self.board, row, index, K) {
+                    self.score = player;
+                }
+                self.player = -player;
+                return;
             }
         }
 
-        // Left diagonals
-        for offset in -6..6 {
-            count_1 = 0;
-            count_n1 = 0;
-            for row in (-offset).max(0)..(6-offset).min(6) {
-                let col = 5-(offset + row);
+        panic!("Out of range.");
+    }
+}
 
-                if self.board[row as usize][col as usize] == -1 { count_n1 += 1; }
-                else {count_n1 = 0; }
+impl<const W: usize, const H: usize, const K: usize> Reversible for ConnectN<W, H, K> {
+    // the column the token was dropped into and the score before the move
+    type Undo = (usize, i8);
 
-                if self.board[row as usize][col as usize] == 1 { count_1 += 1; }
-                else {count_1 = 0; }
+    fn update_with_undo(&mut self, index:usize) -> (usize, i8) {
+        let score = self.score;
+        self.update(index);
+        return (index, score);
+    }
 
-                if count_1 >= 4 {return 1;}
-                if count_n1 >= 4 {return -1;}
+    fn undo(&mut self, (col, score): (usize, i8)) {
+        for row in 0..H {
+            if self.board[row][col] != 0 {
+                self.player = self.board[row][col];
+                self.board[row][col] = 0;
+                self.score = score;
+                return;
             }
         }
 
-        return 0;
+        panic!("Empty column.");
     }
+}
 
-    fn get_available(&self) -> Vec<usize> {
-        return Vec::from_iter( (0..6usize).filter(|&col| self.board[0][col] == 0) );
+// Hand-written evaluation: open threes (K-1 tokens and an empty cell in a line of K)
+// and tokens in the centre columns, squashed into a win probability with a logistic function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connect4Heuristic {
+    pub three_weight: f32,
+    pub centre_weight: f32,
+    pub scale: f32
+}
+
+impl Default for Connect4Heuristic {
+    fn default() -> Connect4Heuristic {
+        return Connect4Heuristic { three_weight: 1.0, centre_weight: 0.25, scale: 0.5 };
     }
+}
 
-    fn update(&mut self, index:usize, player:i8) {
-        for row in (0..6usize).rev() {
-            if self.board[row][index] == 0 {
-                self.board[row][index] = player;
-                return;
+impl Connect4Heuristic {
+    // positive values favour player 1
+    pub fn get_value<const W: usize, const H: usize, const K: usize>(&self, connect4: &ConnectN<W, H, K>) -> f32 {
+        let mut value = 0f32;
+        let length = K as i32;
+
+        for (drow, dcol) in [(0i32, 1i32), (1, 0), (1, 1), (1, -1)] {
+            for row in 0..H as i32 {
+                for col in 0..W as i32 {
+                    let (end_row, end_col) = (row + (length-1)*drow, col + (length-1)*dcol);
+                    if end_row < 0 || end_row >= H as i32 || end_col < 0 || end_col >= W as i32 {
+                        continue;
+                    }
+
+                    let (mut count_1, mut count_n1) = (0usize, 0usize);
+                    for k in 0..length {
+                        match connect4.board[(row + k*drow) as usize][(col + k*dcol) as usize] {
+                            1 => count_1 += 1,
+                            -1 => count_n1 += 1,
+                            _ => ()
+                        }
+                    }
+
+                    if count_1 == K-1 && count_n1 == 0 { value += self.three_weight; }
+                    if count_n1 == K-1 && count_1 == 0 { value -= self.three_weight; }
+                }
             }
         }
 
-        panic!("Out of range.");
+        // the middle two columns, the middle one if the width is odd
+        for row in 0..H {
+            for col in (W-1)/2..=W/2 {
+                value += self.centre_weight * (connect4.board[row][col] as f32);
+            }
+        }
+
+        return value;
     }
 }
 
+impl<const W: usize, const H: usize, const K: usize> Evaluator<ConnectN<W, H, K>> for Connect4Heuristic {
+    fn evaluate(&self, game: &ConnectN<W, H, K>) -> f32 {
+        let value = self.get_value(game) * (game.current_player() as f32);
+        return 1. / (1. + (-self.scale * value).exp());
+    }
+}
+
+#[cfg(test)]
+use rand::seq::SliceRandom;
+
 #[test]
 fn test_connect4_fromstr() {
     let str = "\
@@ -165,7 +283,7 @@ fn test_connect4_fromstr() {
                         XO.XX.\n\
                         XX.OOO\n\
                     ";
-    assert_eq!(Connect4::from_string(str), Some(Connect4 {board: [[1,0,0,0,0,0],[-1,0,0,0,0,0],[1,0,0,-1,0,0],[-1,0,0,1,0,0],[1,-1,0,1,1,0],[1,1,0,-1,-1,-1]]}));
+    assert_eq!(Connect4::from_string(str), Some(Connect4::from_board([[1,0,0,0,0,0],[-1,0,0,0,0,0],[1,0,0,-1,0,0],[-1,0,0,1,0,0],[1,-1,0,1,1,0],[1,1,0,-1,-1,-1]])));
 
     let str = "\
                         X..O..\n\
@@ -315,7 +433,7 @@ fn test_connect4_available() {
 
 #[test]
 fn test_connect4_fmt(){
-    let connect4 = Connect4{ board: [[-1,0,0,1,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,1,0,-1,1],[-1,0,1,0,0,-1]]};
+    let connect4 = Connect4::from_board([[-1,0,0,1,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,1,0,-1,1],[-1,0,1,0,0,-1]]);
 
     let connect4_str = format!("{}", connect4);
     assert_eq!(connect4_str, "O . . X . . \n. . . . . . \n. . . . . . \n. . . . . . \n. . X . O X \nO . X . . O \n");
@@ -341,9 +459,11 @@ fn test_connect4_update(){
                         XO.XX.\n\
                         OOOOO.\n\
                     ";
+    // O has more tokens than X, which no game reaches, so the player to move after the
+    // update differs from the one derived from the token counts and only the boards are compared
     let mut test = connect4.clone();
-    test.update(1, 1);
-    assert_eq!(test, Connect4::from_string(str).unwrap());
+    test.update(1);
+    assert_eq!(test.board, Connect4::from_string(str).unwrap().board);
 
     str = "\
                         X.....\n\
@@ -351,9 +471,309 @@ fn test_connect4_update(){
                         X..O..\n\
                         O..X..\n\
                         XO.XX.\n\
-                        OOOOOO\n\
+                        OOOOOX\n\
                     ";
     test = connect4.clone();
-    test.update(5, -1);
+    test.update(5);
+    assert_eq!(test.board, Connect4::from_string(str).unwrap().board);
+    assert_eq!(test.get_score(), -1);
+
+    // the players alternate
+    str = "\
+                        ......\n\
+                        ......\n\
+                        ......\n\
+                        ..X...\n\
+                        ..O...\n\
+                        ..X...\n\
+                    ";
+    test = Connect4::empty();
+    assert_eq!(test.current_player(), 1);
+    test.update(2);
+    assert_eq!(test.current_player(), -1);
+    test.update(2);
+    test.update(2);
+    assert_eq!(test.current_player(), -1);
     assert_eq!(test, Connect4::from_string(str).unwrap());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_connect4_heuristic(){
+    let heuristic = Connect4Heuristic::default();
+
+    assert_eq!(heuristic.get_value(&Connect4::empty()), 0.);
+    assert_eq!(heuristic.evaluate(&Connect4::empty()), 0.5);
+
+    // X has an open three in the bottom row, O has nothing
+    let str = "\
+                        ......\n\
+                        ......\n\
+                        ......\n\
+                        ......\n\
+                        O.....\n\
+                        XXX..O\n\
+                    ";
+    let connect4 = Connect4::from_string(str).unwrap();
+    assert!((heuristic.get_value(&connect4) - 1.25).abs() < 0.0001);
+    assert_eq!(connect4.current_player(), -1);
+    assert!(heuristic.evaluate(&connect4) < 0.5);
+
+    let str = "\
+                        ......\n\
+                        ......\n\
+                        ......\n\
+                        ......\n\
+                        O.....\n\
+                        XXX.OO\n\
+                    ";
+    let connect4 = Connect4::from_string(str).unwrap();
+    assert!((heuristic.get_value(&connect4) - 1.25).abs() < 0.0001);
+    assert_eq!(connect4.current_player(), 1);
+    let expected = 1. / (1. + (-heuristic.scale * 1.25).exp());
+    assert!((heuristic.evaluate(&connect4) - expected).abs() < 0.0001);
+
+    // O controls the centre
+    let str = "\
+                        ......\n\
+                        ......\n\
+                        ......\n\
+                        ...O..\n\
+                        ..OX..\n\
+                        X.OX..\n\
+                    ";
+    let connect4 = Connect4::from_string(str).unwrap();
+    assert!((heuristic.get_value(&connect4) + 0.25).abs() < 0.0001);
+    assert_eq!(connect4.current_player(), 1);
+    assert!(heuristic.evaluate(&connect4) < 0.5);
+}
+
+#[test]
+fn test_connect4_undo(){
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..100 {
+        let mut connect4 = Connect4::empty();
+        let mut history = Vec::new();
+        while connect4.get_score() == 0 && !connect4.get_available().is_empty() {
+            let index = *connect4.get_available().choose(&mut rng).unwrap();
+            let before = connect4.clone();
+            let undo = connect4.update_with_undo(index);
+
+            let mut restored = connect4.clone();
+            restored.undo(undo);
+            assert_eq!(restored, before);
+
+            history.push((before, undo));
+        }
+
+        while let Some((before, undo)) = history.pop() {
+            connect4.undo(undo);
+            assert_eq!(connect4, before);
+        }
+        assert_eq!(connect4, Connect4::empty());
+    }
+
+    // a move after the game is won keeps the score, and undo brings back the won position
+    let mut won = Connect4::from_string("......\n......\n......\nX.....\nXO....\nXO.O..").unwrap();
+    won.update(0);
+    assert_eq!(won.get_score(), 1);
+    let before = won.clone();
+    let undo = won.update_with_undo(4);
+    assert_eq!(won.get_score(), 1);
+    won.undo(undo);
+    assert_eq!(won, before);
+}
+
+#[test]
+fn test_connect4_symmetries(){
+    // every position up to five plies, and random positions from there
+    let mut rng = rand::thread_rng();
+    let mut positions = vec![Connect4::empty()];
+    let mut next = 0usize;
+    while next < positions.len() {
+        let current = positions[next].clone();
+        next += 1;
+        if current.board.iter().flatten().filter(|&&cell| cell != 0).count() == 5 {
+            continue;
+        }
+        for index in current.get_available() {
+            let mut child = current.clone();
+            child.update(index);
+            if ! positions.contains(&child) {
+                positions.push(child);
+            }
+        }
+    }
+    for _ in 0..100 {
+        let mut connect4 = positions.choose(&mut rng).unwrap().clone();
+        while connect4.get_score() == 0 && !connect4.get_available().is_empty() {
+            positions.push(connect4.clone());
+            connect4.update(*connect4.get_available().choose(&mut rng).unwrap());
+        }
+        positions.push(connect4);
+    }
+
+    for connect4 in positions.iter() {
+        assert_eq!(&connect4.transform(0), connect4);
+        let (key, symmetry) = connect4.canonical_key();
+        assert_eq!(connect4.transform(symmetry).key(), key);
+
+        for symmetry in 0..2 {
+            let transformed = connect4.transform(symmetry);
+            assert_eq!(&transformed.transform(connect4.inverse(symmetry)), connect4);
+            assert_eq!(transformed.get_score(), connect4.get_score());
+            assert_eq!(transformed.current_player(), connect4.current_player());
+            assert_eq!(transformed.canonical_key().0, key);
+
+            let mut available = Vec::from_iter(connect4.get_available().iter().map(|index| connect4.transform_move(symmetry, index)));
+            available.sort();
+            assert_eq!(available, transformed.get_available());
+
+            for index in connect4.get_available() {
+                let mapped = connect4.transform_move(symmetry, &index);
+                assert_eq!(transformed.transform_move(connect4.inverse(symmetry), &mapped), index);
+
+                let (mut played, mut played_transformed) = (connect4.clone(), transformed.clone());
+                played.update(index);
+                played_transformed.update(mapped);
+                assert_eq!(played.transform(symmetry), played_transformed);
+            }
+        }
+    }
+
+    let connect4 = Connect4::from_string("......\n......\n......\n......\n......\nXO....\n").unwrap();
+    assert_eq!(connect4.transform(1), Connect4::from_string("......\n......\n......\n......\n......\n....OX\n").unwrap());
+    assert_eq!(connect4.canonical_key(), (connect4.transform(1).board, 1));
+}
+
+#[test]
+fn test_connect4_standard_board(){
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        ...O...\n\
+                        ...X...\n\
+                        ..OXX..\n\
+                        .XOOXO.\n\
+                    ";
+    let mut connect4 = StandardConnect4::from_string(str).unwrap();
+    assert_eq!(connect4.get_available(), [0, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(connect4.get_score(), 0);
+    assert_eq!(connect4.current_player(), 1);
+    assert_eq!(StandardConnect4::from_string(&connect4.to_string()), Some(connect4.clone()));
+
+    connect4.update(6);
+    assert_eq!(connect4.to_string().lines().last(), Some(". X O O X O X "));
+    assert_eq!(connect4.current_player(), -1);
+    assert_eq!(connect4.transform_move(1, &0), 6);
+
+    // lines through the seventh column
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ....OOO\n\
+                        ...XXXX\n\
+                    ";
+    assert_eq!(StandardConnect4::from_string(str).unwrap().get_score(), 1);
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        ......X\n\
+                        .....XO\n\
+                        ....XOO\n\
+                        ...XOOX\n\
+                    ";
+    assert_eq!(StandardConnect4::from_string(str).unwrap().get_score(), 1);
+
+    // six columns, a gap or seven rows are not a 7x6 board
+    assert_eq!(StandardConnect4::from_string("......\n......\n......\n......\n......\n......\n"), None);
+    assert_eq!(StandardConnect4::from_string(".......\n.......\n.......\n...X...\n.......\n...O...\n"), None);
+    assert_eq!(StandardConnect4::from_string(".......\n.......\n.......\n.......\n.......\n.......\n.......\n"), None);
+}
+
+#[test]
+fn test_connect4_connect5(){
+    let mut connect5 = Connect5::empty();
+    assert_eq!(connect5.get_available().len(), 9);
+
+    // four in a row do not win
+    for col in 1..5 {
+        connect5.update(col);
+        connect5.update(col);
+    }
+    assert_eq!(connect5.get_score(), 0);
+    let str = "\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .OOOO....\n\
+                        .XXXX....\n\
+                    ";
+    assert_eq!(connect5, Connect5::from_string(str).unwrap());
+    connect5.update(5);
+    assert_eq!(connect5.get_score(), 1);
+
+    let str = "\
+                        .........\n\
+                        .........\n\
+                        ....O....\n\
+                        ....O....\n\
+                        ....O...X\n\
+                        ....O..XX\n\
+                        ...XO.XXX\n\
+                    ";
+    assert_eq!(Connect5::from_string(str).unwrap().get_score(), -1);
+
+    // the heuristic counts lines of five with four tokens
+    let heuristic = Connect4Heuristic::default();
+    let str = "\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .XXX.....\n\
+                    ";
+    assert_eq!(heuristic.get_value(&Connect5::from_string(str).unwrap()), 0.);
+    let str = "\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        .........\n\
+                        ..OOO....\n\
+                        .XXXX....\n\
+                    ";
+    assert!((heuristic.get_value(&Connect5::from_string(str).unwrap()) - 2.).abs() < 0.0001);
+}
+
+#[cfg(test)]
+fn check_incremental_score<const W: usize, const H: usize, const K: usize>(games: usize) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..games {
+        let mut connect4 = ConnectN::<W, H, K>::empty();
+        let mut history = Vec::new();
+        while connect4.get_score() == 0 && !connect4.get_available().is_empty() {
+            let index = *connect4.get_available().choose(&mut rng).unwrap();
+            history.push((connect4.clone(), connect4.update_with_undo(index)));
+            assert_eq!(connect4.get_score(), connect4.scan_score(), "{}", connect4);
+            assert_eq!(connect4, ConnectN::from_board(connect4.board));
+        }
+        while let Some((before, undo)) = history.pop() {
+            connect4.undo(undo);
+            assert_eq!(connect4, before);
+        }
+    }
+}
+
+#[test]
+fn test_connect4_incremental_score(){
+    check_incremental_score::<6, 6, 4>(300);
+    check_incremental_score::<7, 6, 4>(200);
+    check_incremental_score::<9, 7, 5>(100);
+}
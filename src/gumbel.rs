@@ -0,0 +1,183 @@
+use rand::Rng;
+use rand_distr::{Distribution, Gumbel};
+
+use super::mcts::{Node, GeneralGame, SearchNode};
+
+// Root search in the style of Gumbel MuZero: the root samples `max_considered` moves without replacement
+// using the Gumbel top-k trick and splits the simulation budget between them by sequential halving.
+// Everything below the root is searched by the usual `Node::propagate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GumbelConfig {
+    pub simulations: usize,
+    pub rollouts: u64,
+    pub max_considered: usize,
+    pub c_visit: f32,
+    pub c_scale: f32,
+    // (alpha, weight) of Dirichlet noise mixed into the root priors before sampling, for self-play
+    pub dirichlet_noise: Option<(f32, f32)>
+}
+
+impl Default for GumbelConfig {
+    fn default() -> GumbelConfig {
+        return GumbelConfig { simulations: 100, rollouts: 1, max_considered: 16, c_visit: 50., c_scale: 1., dirichlet_noise: None };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GumbelSearch {
+    // index into `children` of the selected move
+    pub action: usize,
+    // improved policy target, aligned with `children`
+    pub policy: Vec<f32>
+}
+
+impl<T:GeneralGame> Node<T> {
+    // mean value of the child for the player choosing it, scaled to [0, 1]
+    fn gumbel_q(&self) -> f32 {
+        return ((self.wins as f32 - self.losses as f32) / (self.visits as f32) + 1.) / 2.;
+    }
+
+    fn gumbel_sigma(q: f32, max_visits: u64, config: &GumbelConfig) -> f32 {
+        return (config.c_visit + max_visits as f32) * config.c_scale * q;
+    }
+
+    pub fn gumbel_search<R:Rng>(&mut self, config: &GumbelConfig, rng: &mut R) -> Option<GumbelSearch> {
+        if self.children.is_empty() {
+            self.create_children();
+        }
+        if self.children.is_empty() {
+            return None;
+        }
+        if let Some((alpha, weight)) = config.dirichlet_noise {
+            self.add_dirichlet_noise(alpha, weight, rng);
+        }
+
+        let gumbel = Gumbel::new(0.0f32, 1.0).unwrap();
+        let logits = Vec::from_iter(self.children.iter().map(|ch| ch.prior.max(f32::MIN_POSITIVE).ln()));
+        let noise = Vec::from_iter(self.children.iter().map(|_| gumbel.sample(rng)));
+
+        // sample the considered moves with the Gumbel top-k trick
+        let mut considered = Vec::from_iter(0..self.children.len());
+        considered.sort_by(|&a, &b| (noise[b] + logits[b]).total_cmp(&(noise[a] + logits[a])));
+        considered.truncate(config.max_considered.max(1));
+
+        // sequential halving, whatever is left of the budget is split between the remaining phases
+        let mut remaining_budget = config.simulations;
+        while considered.len() > 1 && remaining_budget > 0 {
+            let phases = (considered.len() as f32).log2().ceil() as usize;
+            let per_move = remaining_budget.div_ceil(phases * considered.len());
+
+            for &index in considered.iter() {
+                for _ in 0..per_move {
+                    if remaining_budget == 0 {
+                        break;
+                    }
+                    remaining_budget -= 1;
+
+                    self.visits += config.rollouts;
+                    let (wins_1, wins_n1) = self.children[index].propagate(config.rollouts, rng);
+                    self.add_result(wins_1, wins_n1);
+                }
+            }
+
+            let max_visits = self.children.iter().map(|ch| ch.visits).max().unwrap_or(0);
+            let mut scores = Vec::from_iter(considered.iter().map(|&i| {
+                let q = if self.children[i].visits > 0 { self.children[i].gumbel_q() } else { 0. };
+                (i, noise[i] + logits[i] + Self::gumbel_sigma(q, max_visits, config))
+            }));
+            scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+            considered = Vec::from_iter(scores.iter().take(considered.len().div_ceil(2)).map(|(i, _)| *i));
+        }
+
+        // improved policy, unvisited moves are completed with the value estimate of this node
+        let value = if self.visits > 0 { ((self.losses as f32 - self.wins as f32) / (self.visits as f32) + 1.) / 2. } else { 0.5 };
+        let max_visits = self.children.iter().map(|ch| ch.visits).max().unwrap_or(0);
+        let completed = Vec::from_iter(self.children.iter().enumerate().map(|(i, ch)| {
+            let q = if ch.visits > 0 { ch.gumbel_q() } else { value };
+            logits[i] + Self::gumbel_sigma(q, max_visits, config)
+        }));
+        let max_completed = completed.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let mut policy = Vec::from_iter(completed.iter().map(|c| (c - max_completed).exp()));
+        let sum = policy.iter().sum::<f32>();
+        for p in policy.iter_mut() {
+            *p /= sum;
+        }
+
+        return Some(GumbelSearch { action: considered[0], policy });
+    }
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+#[cfg(test)]
+use super::connect4::Connect4;
+
+#[test]
+fn test_gumbel_winning_move(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    // O to move, only (0,0) wins immediately
+    let tictactoe = TicTacToe::from_string(".XX\nO..\nOX.").unwrap();
+    for _ in 0..10 {
+        let mut node = Node::new(tictactoe.clone(), None);
+        let config = GumbelConfig { simulations: 64, ..GumbelConfig::default() };
+        let search = node.gumbel_search(&config, &mut rng).unwrap();
+
+        assert_eq!(node.children[search.action].move_index, Some((0, 0)));
+
+        let best = search.policy.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        assert_eq!(node.children[best].move_index, Some((0, 0)));
+    }
+}
+
+#[test]
+fn test_gumbel_budget_and_policy(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut node = Node::new(Connect4::empty(), None);
+    let config = GumbelConfig { simulations: 100, rollouts: 1, max_considered: 4, ..GumbelConfig::default() };
+    let search = node.gumbel_search(&config, &mut rng).unwrap();
+
+    assert_eq!(node.visits, 100);
+    assert_eq!(node.children.iter().map(|ch| ch.visits).sum::<u64>(), 100);
+    assert_eq!(node.children.iter().filter(|ch| ch.visits > 0).count(), 4);
+    assert!(node.children[search.action].visits > 0);
+
+    assert_eq!(search.policy.len(), 6);
+    assert!((search.policy.iter().sum::<f32>() - 1.).abs() < 0.0001);
+    assert!(search.policy.iter().all(|&p| p > 0.));
+
+    // a finished game has nothing to search
+    let tictactoe = TicTacToe::from_string("XXX\nOO.\n...").unwrap();
+    let mut node = Node::new(tictactoe, None);
+    assert_eq!(node.gumbel_search(&config, &mut rng), None);
+}
+
+#[test]
+fn test_gumbel_dirichlet_noise(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut node = Node::new(Connect4::empty(), None);
+    let config = GumbelConfig { simulations: 50, dirichlet_noise: Some((0.5, 0.25)), ..GumbelConfig::default() };
+    let search = node.gumbel_search(&config, &mut rng).unwrap();
+
+    // the priors are no longer uniform but keep mean 1
+    assert!(node.children.iter().any(|ch| (ch.prior - 1.).abs() > 0.0001));
+    assert!((node.children.iter().map(|ch| ch.prior).sum::<f32>() - 6.).abs() < 0.001);
+    assert!((search.policy.iter().sum::<f32>() - 1.).abs() < 0.0001);
+}
+
+#[test]
+fn test_gumbel_seeded(){
+    // the same seed gives the same search
+    let config = GumbelConfig { simulations: 50, dirichlet_noise: Some((0.5, 0.25)), ..GumbelConfig::default() };
+    let searches = Vec::from_iter((0..2).map(|_| {
+        let mut node = Node::new(Connect4::empty(), None);
+        let search = node.gumbel_search(&config, &mut StdRng::seed_from_u64(3)).unwrap();
+        (search, Vec::from_iter(node.children.iter().map(|ch| ch.visits)))
+    }));
+    assert_eq!(searches[0], searches[1]);
+}
@@ -0,0 +1,349 @@
+use std::fmt;
+
+use super::mcts::GeneralGame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HexMove {
+    // (row, column) of the new stone
+    Place(usize, usize),
+    // With the swap rule the second player may take over the first stone instead of placing one,
+    // it is replaced by a stone of their own mirrored in the long diagonal
+    Swap
+}
+
+// Disjoint sets with path halving and union by size, the sets are the connected groups of stones
+#[derive(Debug, Clone)]
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>
+}
+
+impl UnionFind {
+    fn new(count: usize) -> UnionFind {
+        return UnionFind { parent: Vec::from_iter(0..count), size: vec![1; count] };
+    }
+
+    fn find(&mut self, mut element: usize) -> usize {
+        while self.parent[element] != element {
+            self.parent[element] = self.parent[self.parent[element]];
+            element = self.parent[element];
+        }
+        return element;
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            (a, b) = (b, a);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
+// Hex on an N x N rhombus. X (player 1) moves first and connects the top and the bottom edge,
+// O connects the left and the right edge. A full board always has exactly one winner.
+// Cell (row, col) touches (row, col±1), (row-1, col), (row-1, col+1), (row+1, col-1) and (row+1, col).
+#[derive(Debug, Clone)]
+pub struct Hex<const N: usize = 11> {
+    pub board: [[i8; N]; N],
+    pub swap_rule: bool,
+    // cells are row*N + col, followed by the four edges
+    groups: UnionFind,
+    // kept up to date by `update`
+    score: i8,
+    // the player to move and the number of stones on the board, so neither needs a scan of the board
+    player: i8,
+    stones: usize
+}
+
+const NEIGHBOURS : [(isize, isize); 6] = [(0, -1), (0, 1), (-1, 0), (-1, 1), (1, -1), (1, 0)];
+
+impl<const N: usize> Hex<N> {
+    const TOP : usize = N*N;
+    const BOTTOM : usize = N*N + 1;
+    const LEFT : usize = N*N + 2;
+    const RIGHT : usize = N*N + 3;
+
+    pub fn new(swap_rule: bool) -> Hex<N> {
+        return Hex { board: [[0; N]; N], swap_rule, groups: UnionFind::new(N*N + 4), score: 0, player: 1, stones: 0 };
+    }
+
+    pub fn from_board(board: [[i8; N]; N], swap_rule: bool) -> Hex<N> {
+        let mut hex = Hex::new(swap_rule);
+        for (row, cells) in board.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                if cell != 0 {
+                    hex.place(row, col, cell);
+                }
+            }
+        }
+        // X always starts, so the player to move follows from the number of stones
+        let count_1 = board.iter().flatten().filter(|&&cell| cell == 1).count();
+        hex.player = if 2*count_1 > hex.stones {-1} else {1};
+        return hex;
+    }
+
+    // puts a stone on the board and joins it with its neighbours and edges, the other player moves next
+    fn place(&mut self, row: usize, col: usize, player: i8) {
+        self.board[row][col] = player;
+        self.player = -player;
+        self.stones += 1;
+        let cell = row*N + col;
+
+        for (drow, dcol) in NEIGHBOURS {
+            let (r, c) = (row as isize + drow, col as isize + dcol);
+            if r >= 0 && r < N as isize && c >= 0 && c < N as isize && self.board[r as usize][c as usize] == player {
+                self.groups.union(cell, r as usize*N + c as usize);
+            }
+        }
+
+        if player == 1 {
+            if row == 0 { self.groups.union(cell, Self::TOP); }
+            if row == N-1 { self.groups.union(cell, Self::BOTTOM); }
+            if self.groups.find(Self::TOP) == self.groups.find(Self::BOTTOM) {
+                self.score = 1;
+            }
+        }
+        else {
+            if col == 0 { self.groups.union(cell, Self::LEFT); }
+            if col == N-1 { self.groups.union(cell, Self::RIGHT); }
+            if self.groups.find(Self::LEFT) == self.groups.find(Self::RIGHT) {
+                self.score = -1;
+            }
+        }
+    }
+
+    // N rows of X, O and '.', spaces and indentation are ignored, so the output of `Display` can be read back
+    pub fn from_string(val: &str) -> Option<Hex<N>> {
+        return Hex::from_string_with_swap_rule(val, false);
+    }
+
+    pub fn from_string_with_swap_rule(val: &str, swap_rule: bool) -> Option<Hex<N>> {
+        let lines = Vec::from_iter(val.lines().map(|line| line.replace(' ', "")).filter(|line| !line.is_empty()));
+        if lines.len() != N {
+            return None;
+        }
+
+        let mut board = [[0; N]; N];
+        for (row, line) in lines.iter().enumerate() {
+            if line.chars().count() != N {
+                return None;
+            }
+            for (col, s) in line.chars().enumerate() {
+                board[row][col] = match s {
+                    'X' => 1,
+                    'O' => -1,
+                    '.' => 0,
+                    _ => return None
+                };
+            }
+        }
+        return Some(Hex::from_board(board, swap_rule));
+    }
+}
+
+impl<const N: usize> Default for Hex<N> {
+    fn default() -> Hex<N> {
+        return Hex::new(false);
+    }
+}
+
+// the connections follow from the board
+impl<const N: usize> PartialEq for Hex<N> {
+    fn eq(&self, other: &Hex<N>) -> bool {
+        return self.board == other.board && self.swap_rule == other.swap_rule;
+    }
+}
+
+impl<const N: usize> GeneralGame for Hex<N> {
+    type Move = HexMove;
+
+    fn update(&mut self, index: HexMove) {
+        match index {
+            HexMove::Place(row, col) => {
+                self.place(row, col, self.player);
+            }
+            HexMove::Swap => {
+                let (row, col) = (0..N*N).map(|cell| (cell / N, cell % N)).find(|&(row, col)| self.board[row][col] == 1).unwrap();
+                // O's stone is the only one on the board and X moves next, like after any move of O
+                *self = Hex::new(self.swap_rule);
+                self.place(col, row, -1);
+            }
+        }
+    }
+
+    fn get_score(&self) -> i8 {
+        return self.score;
+    }
+
+    fn get_available(&self) -> Vec<HexMove> {
+        if self.score != 0 {
+            return Vec::new();
+        }
+        let mut res = Vec::new();
+        for row in 0..N {
+            for col in 0..N {
+                if self.board[row][col] == 0 {
+                    res.push(HexMove::Place(row, col));
+                }
+            }
+        }
+        // only right after the first stone, after a swap the only stone is O's and X is to move
+        if self.swap_rule && self.stones == 1 && self.player == -1 {
+            res.push(HexMove::Swap);
+        }
+        return res;
+    }
+
+    fn current_player(&self) -> i8 {
+        return self.player;
+    }
+}
+
+// Every row is shifted half a cell further right, which shows the hexagonal neighbours:
+//  X . .
+//   . O .
+//    . . .
+impl<const N: usize> fmt::Display for Hex<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..N {
+            write!(f, "{}", " ".repeat(i)).unwrap();
+            for j in 0..N {
+                write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
+            }
+            writeln!(f).unwrap();
+        }
+        write!(f, "")
+    }
+}
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+// whether the stones of `player` connect their edges, by a flood fill, also the reference for the conformance tests
+#[cfg(test)]
+pub fn flood_fill_winner<const N: usize>(board: &[[i8; N]; N], player: i8) -> bool {
+    let start = |row: usize, col: usize| if player == 1 {row == 0} else {col == 0};
+    let end = |row: usize, col: usize| if player == 1 {row == N-1} else {col == N-1};
+
+    let mut stack = Vec::from_iter((0..N*N).map(|cell| (cell / N, cell % N)).filter(|&(row, col)| board[row][col] == player && start(row, col)));
+    let mut seen = [[false; N]; N];
+    while let Some((row, col)) = stack.pop() {
+        if seen[row][col] {
+            continue;
+        }
+        seen[row][col] = true;
+        if end(row, col) {
+            return true;
+        }
+        for (drow, dcol) in NEIGHBOURS {
+            let (r, c) = (row as isize + drow, col as isize + dcol);
+            if r >= 0 && r < N as isize && c >= 0 && c < N as isize && board[r as usize][c as usize] == player {
+                stack.push((r as usize, c as usize));
+            }
+        }
+    }
+    return false;
+}
+
+#[test]
+fn test_hex_edge_connections(){
+    // a straight column connects top and bottom
+    let hex = Hex::<3>::from_string(".X.\n.XO\nOX.").unwrap();
+    assert_eq!(hex.get_score(), 1);
+
+    // the neighbour down and to the left is connected, down and to the right is not
+    let hex = Hex::<3>::from_string("..X\n.XO\nXO.").unwrap();
+    assert_eq!(hex.get_score(), 1);
+    let hex = Hex::<3>::from_string("X..\n.X.\nOOX").unwrap();
+    assert_eq!(hex.get_score(), 0);
+
+    // O connects left and right
+    let hex = Hex::<3>::from_string("X..\n.XX\nOOO").unwrap();
+    assert_eq!(hex.get_score(), -1);
+    let hex = Hex::<4>::from_string("XX..\nX..O\n.OO.\nOXX.").unwrap();
+    assert_eq!(hex.get_score(), -1);
+
+    // the winning move
+    let mut hex = Hex::<4>::from_string("..X.\n.OXO\n.X..\nO...").unwrap();
+    assert_eq!(hex.get_score(), 0);
+    assert_eq!(hex.current_player(), 1);
+    // down and to the right of (2, 1)
+    hex.update(HexMove::Place(3, 2));
+    assert_eq!(hex.get_score(), 0);
+    hex.update(HexMove::Place(0, 0));
+    hex.update(HexMove::Place(3, 1));
+    assert_eq!(hex.get_score(), 1);
+    assert_eq!(hex.get_available(), []);
+}
+
+#[test]
+fn test_hex_random_games(){
+    // every full board has exactly one winner, the union-find agrees with a flood fill after every move
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..200 {
+        let mut hex = Hex::<5>::default();
+        let mut cells = Vec::from_iter((0..25).map(|cell| HexMove::Place(cell / 5, cell % 5)));
+        cells.shuffle(&mut rng);
+
+        let mut score = 0;
+        for index in cells {
+            let player = hex.current_player();
+            if let HexMove::Place(row, col) = index {
+                hex.place(row, col, player);
+            }
+            let x_connected = flood_fill_winner(&hex.board, 1);
+            let o_connected = flood_fill_winner(&hex.board, -1);
+            assert!(!(x_connected && o_connected));
+            if score == 0 {
+                score = if x_connected {1} else if o_connected {-1} else {0};
+            }
+            assert_eq!(hex.get_score(), score, "{}", hex);
+        }
+        assert_ne!(score, 0);
+    }
+}
+
+#[test]
+fn test_hex_swap_rule(){
+    let mut hex = Hex::<5>::new(true);
+    assert!(! hex.get_available().contains(&HexMove::Swap));
+    hex.update(HexMove::Place(1, 3));
+    assert_eq!(hex.get_available().len(), 25);
+    assert_eq!(hex.get_available().last(), Some(&HexMove::Swap));
+
+    let mut swapped = hex.clone();
+    swapped.update(HexMove::Swap);
+    assert_eq!(swapped.board, Hex::<5>::from_string(".....\n.....\n.....\n.O...\n.....").unwrap().board);
+    assert_eq!(swapped.current_player(), 1);
+    assert!(! swapped.get_available().contains(&HexMove::Swap));
+    assert_eq!(swapped.get_available().len(), 24);
+    // the same positions read from a string
+    let read = Hex::<5>::from_string_with_swap_rule(".....\n...X.\n.....\n.....\n.....", true).unwrap();
+    assert_eq!(read, hex);
+    assert_eq!(read.get_available(), hex.get_available());
+    let read = Hex::<5>::from_string_with_swap_rule(".....\n.....\n.....\n.O...\n.....", true).unwrap();
+    assert_eq!(read, swapped);
+    assert_eq!(read.current_player(), 1);
+    assert!(! read.get_available().contains(&HexMove::Swap));
+    assert!(! Hex::<5>::from_string(".....\n...X.\n.....\n.....\n.....").unwrap().get_available().contains(&HexMove::Swap));
+
+    hex.update(HexMove::Place(2, 2));
+    assert!(! hex.get_available().contains(&HexMove::Swap));
+    assert!(! Hex::<5>::new(false).get_available().contains(&HexMove::Swap));
+}
+
+#[test]
+fn test_hex_display(){
+    let hex = Hex::<3>::from_string("X..\n.O.\n..X").unwrap();
+    assert_eq!(hex.to_string(), "X . . \n . O . \n  . . X \n");
+    assert_eq!(Hex::<3>::from_string(&hex.to_string()), Some(hex));
+
+    assert_eq!(Hex::<11>::default().to_string().lines().count(), 11);
+    assert_eq!(Hex::<3>::from_string("X..\n.O.\n"), None);
+    assert_eq!(Hex::<3>::from_string("X..\n.O.\n..Y"), None);
+}
@@ -0,0 +1,215 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use super::mcts::{Reversible, SearchNode, uct_score, UPPER_BOUND_CONSTANT};
+
+// Search tree that does not store the game in its nodes. The search walks the tree
+// with a single mutable game, applying moves on the way down and undoing them on the way back.
+// `M` is the move type of the game.
+#[derive(Debug,PartialEq, Clone)]
+pub struct InPlaceNode<M> {
+    // the player who made the move leading to this node, the statistics are from their perspective
+    pub mover: i8,
+    pub visits: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub children: Vec<InPlaceNode<M>>,
+    created_children: bool,
+    // the move leading to this node, None for the root
+    pub move_index: Option<M>,
+    // relative weight of the exploration term, 1.0 is neutral
+    pub prior: f32,
+    // the exploration constant of `uct_score`, inherited by the children
+    pub exploration: f32
+}
+
+impl<M> SearchNode for InPlaceNode<M> {
+    fn mover(&self) -> i8 {
+        return self.mover;
+    }
+    fn visits(&self) -> u64 {
+        return self.visits;
+    }
+    fn results_mut(&mut self) -> (&mut u64, &mut u64) {
+        return (&mut self.wins, &mut self.losses);
+    }
+    fn children(&self) -> &[InPlaceNode<M>] {
+        return &self.children;
+    }
+    fn created_children(&self) -> bool {
+        return self.created_children;
+    }
+    fn get_score(&self, parent_visits: u64) -> f32 {
+        return uct_score(self.wins, self.losses, self.visits, parent_visits, self.prior, self.exploration);
+    }
+}
+
+impl<M:Clone> InPlaceNode<M> {
+    pub fn new(mover: i8, move_index : Option<M>) -> InPlaceNode<M> {
+        return InPlaceNode {mover, visits: 0, wins: 0, losses: 0, children: Vec::new(), created_children: false, move_index, prior: 1.0, exploration: UPPER_BOUND_CONSTANT};
+    }
+
+    // root node for searching `game`
+    pub fn root<T:Reversible<Move = M>>(game: &T) -> InPlaceNode<M> {
+        return InPlaceNode::new(-game.current_player(), None);
+    }
+
+    // random rollout from the current state of `game`, the game is restored afterwards
+    pub fn rollout<T:Reversible<Move = M>, R:Rng>(game: &mut T, rng: &mut R) -> i8 {
+        let mut history = Vec::new();
+
+        let result = loop {
+            let score = game.get_score();
+            if score != 0 {
+                break score;
+            }
+
+            let available = game.get_available();
+
+            if available.is_empty() {
+                break 0;
+            }
+
+            let index = available.choose(rng).unwrap().clone();
+            history.push(game.update_with_undo(index));
+        };
+
+        while let Some(undo) = history.pop() {
+            game.undo(undo);
+        }
+
+        return result;
+    }
+
+    fn create_children<T:Reversible<Move = M>>(&mut self, game: &T) {
+        self.created_children = true;
+
+        // If someone already won, there is no point in creating children
+        if game.get_score() != 0{
+            return;
+        }
+
+        for index in game.get_available() {
+            let mut child = InPlaceNode::new(game.current_player(), Some(index));
+            child.exploration = self.exploration;
+            self.children.push(child);
+        }
+    }
+
+    // `game` must be the position of this node, it is restored before returning
+    pub fn propagate<T:Reversible<Move = M>, R:Rng>(&mut self, game: &mut T, rollouts: u64, rng: &mut R) -> (u64, u64) {
+        self.visits += rollouts;
+
+        // if someone has already won, just return the winner
+        let score = game.get_score();
+        if score != 0 {
+            let (wins_1, wins_n1) = match score {
+                1 => (rollouts, 0),
+                -1 => (0, rollouts),
+                _ => panic!("Invalid score {}.", score)
+            };
+            self.add_result(wins_1, wins_n1);
+            return (wins_1, wins_n1);
+        }
+
+        let (mut wins_1, mut wins_n1) = (0u64, 0u64);
+
+        if ! self.created_children {
+            self.create_children(game);
+
+            for _ in 0..rollouts {
+                let res = Self::rollout(game, rng);
+
+                if res == 1{
+                    wins_1 += 1;
+                }
+                else if res == -1 {
+                    wins_n1 += 1;
+                }
+            }
+        }
+        else if let Some(next) = self.get_child_with_highest_score(rng) {
+            let index = self.children[next].move_index.clone().unwrap();
+            let undo = game.update_with_undo(index);
+            (wins_1, wins_n1) = self.children[next].propagate(game, rollouts, rng);
+            game.undo(undo);
+        }
+
+        self.add_result(wins_1, wins_n1);
+
+        return (wins_1, wins_n1);
+    }
+
+    pub fn predict<T:Reversible<Move = M>>(&mut self, game: &mut T, iters: usize, rollouts: u64) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..iters {
+            self.propagate(game, rollouts, &mut rng);
+        }
+    }
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+#[cfg(test)]
+use super::connect4::Connect4;
+
+#[test]
+fn test_inplace_rollout(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut tictactoe = TicTacToe::from_string("XX.\nOOX\nOXX").unwrap();
+    assert_eq!(InPlaceNode::rollout(&mut tictactoe, &mut rng), -1);
+    assert_eq!(tictactoe, TicTacToe::from_string("XX.\nOOX\nOXX").unwrap());
+
+    let mut tictactoe = TicTacToe::from_string("XX.\nOOX\nOXO").unwrap();
+    assert_eq!(InPlaceNode::rollout(&mut tictactoe, &mut rng), 1);
+    assert_eq!(tictactoe, TicTacToe::from_string("XX.\nOOX\nOXO").unwrap());
+
+    let mut connect4 = Connect4::empty();
+    for _ in 0..20 {
+        InPlaceNode::rollout(&mut connect4, &mut rng);
+        assert_eq!(connect4, Connect4::empty());
+    }
+}
+
+#[test]
+fn test_inplace_predict(){
+    // O to move, (0,0) wins immediately
+    let mut tictactoe = TicTacToe::from_string(".XX\nO..\nOX.").unwrap();
+    let mut node = InPlaceNode::root(&tictactoe);
+
+    node.predict(&mut tictactoe, 300, 1);
+    assert_eq!(tictactoe, TicTacToe::from_string(".XX\nO..\nOX.").unwrap());
+    assert_eq!(node.visits, 300);
+    assert_eq!(node.children.len(), 4);
+    assert_eq!(node.get_most_visited_child().unwrap().move_index, Some((0, 0)));
+
+    let mut connect4 = Connect4::empty();
+    let mut node = InPlaceNode::root(&connect4);
+    node.predict(&mut connect4, 200, 2);
+    assert_eq!(connect4, Connect4::empty());
+    assert_eq!(node.visits, 400);
+    assert_eq!(node.children.iter().map(|ch| ch.visits).sum::<u64>(), 398);
+}
+
+#[test]
+fn test_inplace_exploration(){
+    // the same statistics give the same scores as `Node`
+    let mut tictactoe = TicTacToe::from_string(".XX\nO..\nOX.").unwrap();
+    let mut node = InPlaceNode::root(&tictactoe);
+    node.exploration = 0.5;
+    node.predict(&mut tictactoe, 50, 1);
+    assert!(node.children.iter().all(|ch| ch.exploration == 0.5));
+
+    let mut reference = super::mcts::Node::new(tictactoe.clone(), None);
+    reference.visits = 50;
+    reference.create_children();
+    for (child, inplace) in reference.children.iter_mut().zip(node.children.iter_mut()) {
+        inplace.prior = 0.7;
+        (child.visits, child.wins, child.losses, child.prior, child.exploration) = (inplace.visits, inplace.wins, inplace.losses, 0.7, 0.5);
+        assert_eq!(child.get_score(50), inplace.get_score(50));
+    }
+}
@@ -1,15 +1,48 @@
 #![allow(dead_code)]
 
+mod mnk;
 mod tictactoe;
 //use tictactoe::TicTacToe;
 mod mcts;
+mod gumbel;
+mod inplace;
+mod batch;
+mod negamax;
+mod pns;
+mod solver;
+mod book;
+use book::{OpeningBook, BookAgent};
+mod agent;
+use agent::{Agent, HumanAgent, MctsAgent, play_game};
+mod tournament;
+use tournament::{Entrant, TournamentConfig, Schedule, Sprt, run_tournament};
+mod tuning;
+use tuning::{Parameter, SpsaConfig, spsa};
+mod scaling;
+use scaling::{Budget, ScalingConfig, run_scaling};
+#[cfg(test)]
+mod conformance;
+use gumbel::GumbelConfig;
 use core::panic;
 
 use mcts::{Node, GeneralGame};
 
 mod connect4;
-use connect4::Connect4;
+mod bitconnect4;
+mod othello;
+mod hex;
+use bitconnect4::BitConnect4;
+use connect4::{Connect4, ConnectN, StandardConnect4};
+
+mod puzzle;
+use puzzle::{nested_monte_carlo, nrpa};
+mod samegame;
+use samegame::SameGame;
 use rand::prelude::ThreadRng;
+use rand::{SeedableRng, rngs::StdRng};
+
+// the opening book used by the bots if it exists, see `build_connect4_book`
+const BOOK_PATH : &str = "connect4.book";
 
 fn play_connect4_against_computer() {
     let mut line = String::new();
@@ -24,27 +57,34 @@ fn play_connect4_against_computer() {
     let max_eval_rollouts = line.parse::<usize>().unwrap();
     println!("Settings: bot:{} eval:{}", max_bot_rollouts, max_eval_rollouts);
 
-    fn index_from_input() -> Option<usize> {
-        let mut line = String::new();
-    
-        std::io::stdin().read_line(&mut line).unwrap();
-        
-        line.retain(|c| !c.is_whitespace());
-    
+    line = String::new();
+    println!("Enter board width (6 or 7, the book only covers 6): ");
+    std::io::stdin().read_line(&mut line).unwrap();
+    line.retain(|c| !c.is_whitespace());
+
+    if line == "7" {
+        let bot = MctsAgent::new(max_bot_rollouts, 1, rand::thread_rng());
+        play_against_computer(StandardConnect4::empty(), Box::new(bot), max_eval_rollouts);
+        return;
+    }
+    let bot = MctsAgent::new(max_bot_rollouts, 1, rand::thread_rng());
+    let computer : Box<dyn Agent<Connect4>> = match OpeningBook::load(BOOK_PATH) {
+        Ok(book) => Box::new(BookAgent { book, fallback: bot }),
+        Err(_) => Box::new(bot)
+    };
+    play_against_computer(Connect4::empty(), computer, max_eval_rollouts);
+}
+
+fn play_against_computer<const W: usize, const H: usize, const K: usize>(mut board: ConnectN<W, H, K>, mut computer: Box<dyn Agent<ConnectN<W, H, K>>>, max_eval_rollouts: usize) {
+    fn index_from_input(line: &str) -> Option<usize> {
         let index = line.parse::<usize>();
         if index.is_err() { return None; }
-        let index = index.unwrap();
-        if index < 1 || index > 6 { return None; }
 
-        return Some(index-1);
+        return index.unwrap().checked_sub(1);
     }
 
     let mut rng = rand::thread_rng();
-    
-
-    let mut board = Connect4::empty();
-
-    let mut player = 1i8;
+    let mut human = HumanAgent { prompt: format!("Select where do you want to place the token (1-{}):", W), parse: index_from_input };
 
     let mut round = 0usize;
     while board.get_score() == 0 && board.get_available().len() > 0 {
@@ -52,47 +92,33 @@ fn play_connect4_against_computer() {
 
         // Print evaluation
         {
-            let mut node = Node::new(board.clone(), player, 0);
+            let mut node = Node::new(board.clone(), None);
             for _ in 0..max_eval_rollouts {
                 node.propagate(1, &mut rng);
             }
             let mut wins = (node.wins as f32)/(node.visits as f32)*100.;
             let mut losses = (node.losses as f32)/(node.visits as f32)*100.;
-            if player == 1 {
+            if board.current_player() == 1 {
                 (wins, losses) = (losses, wins);
             }
             println!("Human wins: {:0.1}%, Computer wins: {:0.1}%", wins, losses);
-            //println!("Best move is {}", node.get_most_visited_child().unwrap().move_index+1);
+            //println!("Best move is {}", node.get_most_visited_child().unwrap().move_index.unwrap()+1);
         }
         
         // Human
-        if player == 1 {
+        if board.current_player() == 1 {
             round += 1;
             println!("\u{001b}[32;1mRound {}\u{001b}[0m", round);
 
-
-            let mut index : Option<usize> = None;
-            let available = board.get_available();
-            while index.is_none() || !available.contains(&index.unwrap()){
-                println!("Select where do you want to place the token (1-6):");
-                index = index_from_input();
-            }
-
-            board.update(index.unwrap(), player);
+            let index = human.choose_move(&board);
+            computer.opponent_moved(&board, &index);
+            board.update(index);
         }
         // Computer
-        else if player == -1 {
-            let mut node = Node::new(board.clone(), player, 0);
-            for _ in 0..max_bot_rollouts {
-                node.propagate(1, &mut rng);
-            }
-
-            let index = node.get_most_visited_child().unwrap().move_index;
-
-            board.update(index, player);
+        else {
+            let index = computer.choose_move(&board);
+            board.update(index);
         }
-
-        player *= -1;
     }
 
     println!("{}", board);
@@ -100,28 +126,106 @@ fn play_connect4_against_computer() {
 }
 
 fn simulate_game(bot1_evals: usize, bot2_evals: usize, bot1_rollouts:usize, bot2_rollouts:usize) -> i8 {
-    let mut board = Connect4::empty();
+    let mut bot1 = MctsAgent::new(bot1_evals, bot1_rollouts as u64, rand::thread_rng());
+    let mut bot2 = MctsAgent::new(bot2_evals, bot2_rollouts as u64, rand::thread_rng());
 
-    let mut rng = rand::thread_rng();
+    return play_game(&Connect4::empty(), &mut bot1, &mut bot2).unwrap().result;
+}
+
+// Plays MCTS bots with different numbers of iterations against each other
+fn run_connect4_tournament() {
+    let entrants = Vec::from_iter([100, 1000, 10000].into_iter().map(|iterations| Entrant::<Connect4> {
+        name: format!("mcts {}", iterations),
+        make: Box::new(move |seed| Box::new(MctsAgent::new(iterations, 1, StdRng::seed_from_u64(seed))))
+    }));
+    let config = TournamentConfig {
+        schedule: Schedule::RoundRobin,
+        games_per_pair: 200,
+        threads: std::thread::available_parallelism().map_or(4, |threads| threads.get()),
+        sprt: Some(Sprt { elo0: 0., elo1: 50., alpha: 0.05, beta: 0.05 }),
+        seed: 0
+    };
+
+    let result = run_tournament(&Connect4::empty(), &entrants, &config);
+    print!("{}", result.to_csv());
+    result.write_csv("tournament.csv").unwrap();
+    result.write_json("tournament.json").unwrap();
+}
 
-    let mut player = 1i8;
+// Tunes the exploration constant and the rollouts per iteration of the Connect4 bot
+fn tune_connect4_mcts() {
+    let parameters = [Parameter::new("exploration", mcts::UPPER_BOUND_CONSTANT as f64, 0.1, 6., 0.5), Parameter::new("rollouts", 1., 1., 20., 2.)];
+    let make = |values: &[f64], seed: u64| -> Box<dyn Agent<Connect4>> {
+        let mut agent = MctsAgent::new(1000, values[1].round() as u64, StdRng::seed_from_u64(seed));
+        agent.exploration = values[0] as f32;
+        return Box::new(agent);
+    };
+    let config = SpsaConfig {
+        threads: std::thread::available_parallelism().map_or(4, |threads| threads.get()),
+        ..SpsaConfig::default()
+    };
+
+    println!("{}", spsa(&Connect4::empty(), &parameters, make, &config));
+}
 
-    while board.get_score() == 0 && board.get_available().len() > 0 {
-        let mut node = Node::new(board.clone(), player, 0);
-        let max_evals = if player == 1 {bot1_evals} else {bot2_evals};
-        let rollouts = if player == 1 {bot1_rollouts} else {bot2_rollouts};
+// Measures the strength of the Connect4 bot for growing budgets, to choose the defaults of the interactive game
+fn connect4_scaling() {
+    let budgets = Vec::from_iter([1, 4].into_iter().flat_map(|rollouts| [100, 300, 1000, 3000, 10000].into_iter().map(move |iterations| Budget::new(iterations, rollouts))));
+    let config = ScalingConfig {
+        games_per_budget: 100,
+        threads: std::thread::available_parallelism().map_or(4, |threads| threads.get()),
+        seed: 0
+    };
+
+    let experiment = run_scaling(&Connect4::empty(), &budgets, Budget::new(1000, 1), &config);
+    print!("{}", experiment.to_csv());
+    experiment.write_csv("scaling.csv").unwrap();
+    experiment.write_svg("scaling.svg").unwrap();
+}
 
-        for _ in 0..max_evals {
-            node.propagate(rollouts as u64, &mut rng);
+// Random rollouts per second from the empty board, for the array and the bitboard version of Connect4
+fn benchmark_rollouts() {
+    fn rollouts_per_second<T:GeneralGame>(game: T) -> f64 {
+        let mut rng = StdRng::seed_from_u64(0);
+        let node = Node::new(game, None);
+        let start = std::time::Instant::now();
+        let mut rollouts = 0u64;
+        while start.elapsed().as_secs_f64() < 2. {
+            for _ in 0..1000 {
+                node.rollout(&mut rng);
+            }
+            rollouts += 1000;
         }
+        return rollouts as f64 / start.elapsed().as_secs_f64();
+    }
 
-        let best_move = node.get_most_visited_child().unwrap().move_index;
-        board.update(best_move, player);
+    println!("Connect4: {:.0} rollouts/s", rollouts_per_second(Connect4::empty()));
+    println!("BitConnect4: {:.0} rollouts/s", rollouts_per_second(BitConnect4::empty()));
+}
+
+// Searches the first plies deeply and writes the results to the opening book
+fn build_connect4_book() {
+    let mut rng = rand::thread_rng();
+    let book = OpeningBook::build(4, 100000, 1, &mut rng);
+    book.save(BOOK_PATH).unwrap();
+    println!("Wrote {} positions to {}", book.len(), BOOK_PATH);
+}
 
-        player *= -1;
+fn solve_samegame() {
+    let mut rng = rand::thread_rng();
+
+    let samegame = SameGame::random(8, 8, 3, &mut rng);
+    println!("{}", samegame);
+
+    for level in 0..3 {
+        let (score, sequence) = nested_monte_carlo(&samegame, level, &mut rng);
+        println!("NMCS level {}: score {}, {} moves", level, score, sequence.len());
     }
 
-    return board.get_score();
+    for level in 1..3 {
+        let (score, sequence) = nrpa(&samegame, level, 100, 1., &mut rng);
+        println!("NRPA level {}: score {}, {} moves", level, score, sequence.len());
+    }
 }
 
 fn train_neural_net() {
@@ -145,28 +249,31 @@ fn train_neural_net() {
         neural_nets::losses::CrossEntropy1D<6>
     );
 
-    fn flip_board(connect4: &mut Connect4) {
-        for i in 0..6 {
-            for j in 0..6{
-                connect4.board[i][j] *= -1;
-            }
-        }
-    }
-
+    // the board from the perspective of the player to move, opponent tokens first
     fn get_input(board : &Connect4) -> [f32;72]{
         let mut out = [0.0f32; 72];
+        let player = board.current_player();
 
         for i in 0..6 {
             for j in 0..6 {
-                if board.board[i][j] == -1 { out[i*6+j] = 1.; }
-                if board.board[i][j] == 1 { out[i*6+j + 36] = 1.; }
+                if board.board[i][j] == -player { out[i*6+j] = 1.; }
+                if board.board[i][j] == player { out[i*6+j + 36] = 1.; }
             }
         }
 
         return out;
     }
 
-    let mut root_node = Node::<Connect4>::new(Connect4::empty(), 1, 0);
+    // exploration noise mixed into the root priors of every self-play search
+    const DIRICHLET_ALPHA : f32 = 0.5;
+    const DIRICHLET_WEIGHT : f32 = 0.25;
+
+    // the Gumbel root search gives a policy improvement even with a small simulation budget
+    let gumbel_config = GumbelConfig { simulations: 100, rollouts: 1, dirichlet_noise: Some((DIRICHLET_ALPHA, DIRICHLET_WEIGHT)), ..GumbelConfig::default() };
+
+    let mut rng = rand::thread_rng();
+
+    let mut root_node = Node::<Connect4>::new(Connect4::empty(), None);
 
     let mut model = Box::new(MyModel::new());
 
@@ -180,34 +287,19 @@ fn train_neural_net() {
 
             // reset board if needed
             if root_node.game.get_score() != 0 || root_node.game.get_available().len() == 0 {
-                root_node = Node::<Connect4>::new(Connect4::empty(), 1, 0);
+                root_node = Node::<Connect4>::new(Connect4::empty(), None);
             }
 
-            root_node.predict(100, 1);
-            
-            assert!(root_node.player == 1);
+            let search = root_node.gumbel_search(&gumbel_config, &mut rng);
             
-            // create ground_truth vector
-            groundtruth = [0f32;6];
-            let sum = root_node.children.iter().map(|ch| ch.visits).sum::<u64>() as f32;
-            for ch in root_node.children.iter() {
-                groundtruth[ch.move_index] = (ch.visits as f32) / sum;
-            }
-            let mut sum = 0f32;
-            for i in 0..6{
-                //groundtruth[i] = groundtruth[i]*groundtruth[i];
-                sum += groundtruth[i];
-            }
-            assert!(sum > 0.);
-            for i in 0..6{
-                groundtruth[i] = groundtruth[i]/ sum;
-            }
-
-            // get best next move
-            if let Some(next) = root_node.get_most_visited_child() {
-                root_node = next.clone();
-                root_node.player = 1;
-                flip_board(&mut root_node.game);
+            // get best next move and the improved policy as ground truth
+            if let Some(search) = search {
+                groundtruth = [0f32;6];
+                for (ch, p) in root_node.children.iter().zip(search.policy.iter()) {
+                    groundtruth[ch.move_index.unwrap()] = *p;
+                }
+
+                root_node = root_node.children[search.action].clone();
             }
             else {
                 println!("{}", root_node.game);
@@ -1,53 +1,327 @@
 use core::panic;
+use std::fmt;
+use std::hash::Hash;
 
 use rand::prelude::IteratorRandom;
 use rand::seq::SliceRandom;
-use rand::rngs::ThreadRng;
+use rand::Rng;
+use rand_distr::{Distribution, Gamma};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError<M = usize> {
+    // the move is not one of `get_available`
+    IllegalMove(M),
+    // the game has already been won
+    GameOver,
+    // `get_score` returned something other than 1, 0 or -1
+    InvalidScore(i8)
+}
+
+impl<M:fmt::Debug> fmt::Display for GameError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::IllegalMove(index) => write!(f, "Illegal move {:?}", index),
+            GameError::GameOver => write!(f, "The game is already over"),
+            GameError::InvalidScore(score) => write!(f, "Invalid score {}", score)
+        }
+    }
+}
+
+impl<M:fmt::Debug> std::error::Error for GameError<M> {}
 
 pub trait GeneralGame : Clone {
-    fn update(&mut self, index:usize, player:i8);
+    type Move : Clone + Eq + fmt::Debug;
+
+    // plays the move for `current_player`
+    fn update(&mut self, index:Self::Move);
     fn get_score(&self) -> i8;
-    fn get_available(&self) -> Vec<usize>;
+    fn get_available(&self) -> Vec<Self::Move>;
     //fn get_available(&self) -> (Vec<usize>, Vec<f32>);
+    // the player to move, 1 or -1
+    fn current_player(&self) -> i8;
+
+    // Like `update`, but checks that the move is legal first
+    fn try_update(&mut self, index:Self::Move) -> Result<(), GameError<Self::Move>> {
+        match self.get_score() {
+            0 => (),
+            1 | -1 => return Err(GameError::GameOver),
+            score => return Err(GameError::InvalidScore(score))
+        }
+        if ! self.get_available().contains(&index) {
+            return Err(GameError::IllegalMove(index));
+        }
+
+        self.update(index);
+        return Ok(());
+    }
+}
+
+// Games whose moves are plain indices can implement this simpler trait instead of `GeneralGame`
+pub trait IndexedGame : Clone {
+    fn update(&mut self, index:usize);
+    fn get_score(&self) -> i8;
+    fn get_available(&self) -> Vec<usize>;
+    fn current_player(&self) -> i8;
+}
+
+impl<G:IndexedGame> GeneralGame for G {
+    type Move = usize;
+
+    fn update(&mut self, index:usize) {
+        IndexedGame::update(self, index);
+    }
+    fn get_score(&self) -> i8 {
+        return IndexedGame::get_score(self);
+    }
+    fn get_available(&self) -> Vec<usize> {
+        return IndexedGame::get_available(self);
+    }
+    fn current_player(&self) -> i8 {
+        return IndexedGame::current_player(self);
+    }
+}
+
+// Games that can take back a move, so the search can walk the tree with a single mutable state
+pub trait Reversible : GeneralGame {
+    type Undo;
+    fn update_with_undo(&mut self, index:Self::Move) -> Self::Undo;
+    fn undo(&mut self, undo: Self::Undo);
+}
+
+// Games whose positions have symmetries, e.g. mirrored boards.
+// Symmetry 0 is the identity, every symmetry maps legal moves to legal moves and keeps the score.
+pub trait Symmetries : GeneralGame {
+    // identifies a position, used for transposition keys
+    type Key : Ord + Clone + Hash + fmt::Debug;
+
+    fn symmetry_count(&self) -> usize;
+    fn transform(&self, symmetry: usize) -> Self;
+    // the move in the transformed position corresponding to `index`
+    fn transform_move(&self, symmetry: usize, index: &Self::Move) -> Self::Move;
+    // the symmetry undoing `symmetry`
+    fn inverse(&self, symmetry: usize) -> usize;
+    fn key(&self) -> Self::Key;
+
+    // The smallest key of all symmetric positions and the symmetry producing it,
+    // so symmetric positions share the same transposition key
+    fn canonical_key(&self) -> (Self::Key, usize) {
+        let mut best = (self.key(), 0);
+        for symmetry in 1..self.symmetry_count() {
+            let key = self.transform(symmetry).key();
+            if key < best.0 {
+                best = (key, symmetry);
+            }
+        }
+        return best;
+    }
+}
+
+// default weight of the exploration term of `uct_score`
+pub const UPPER_BOUND_CONSTANT : f32 = 1.4142*2.;
+
+// Upper confidence bound of a move, `prior` scales the exploration term
+pub fn uct_score(wins: u64, losses: u64, visits: u64, parent_visits: u64, prior: f32, exploration: f32) -> f32 {
+    if visits == 0 {
+        return f32::INFINITY;
+    }
+
+    let fwins = (wins as f32)  - (losses as f32);
+    let fvisits = visits as f32;
+    let fparent_visits = parent_visits as f32;
+
+    return (fwins)/(fvisits) + exploration * prior * (fparent_visits.ln() / fvisits).sqrt();
+}
+
+// Samples the result of a game from the win probability of `player`
+pub fn sample_result<R:Rng>(probability: f32, player: i8, rng: &mut R) -> i8 {
+    if rng.gen::<f32>() < probability {
+        return player;
+    }
+    return -player;
+}
+
+// Static evaluation of a position that is not finished yet
+pub trait Evaluator<T:GeneralGame> {
+    // probability that the player to move wins the game
+    fn evaluate(&self, game: &T) -> f32;
+}
+
+// How the value of a newly expanded node is estimated
+pub enum Playout<'a, T:GeneralGame> {
+    // random moves until the game is finished
+    Random,
+    // the evaluator replaces the rollout entirely
+    Evaluate(&'a dyn Evaluator<T>),
+    // at most the given number of random plies, then the evaluator
+    Truncated(&'a dyn Evaluator<T>, usize)
+}
+
+// Statistics and child selection shared by `Node` and `InPlaceNode`
+pub trait SearchNode : Sized {
+    // the player who made the move leading to this node, the statistics are from their perspective
+    fn mover(&self) -> i8;
+    fn visits(&self) -> u64;
+    // the counters of wins and losses
+    fn results_mut(&mut self) -> (&mut u64, &mut u64);
+    fn children(&self) -> &[Self];
+    fn created_children(&self) -> bool;
+    // UCT score of this node as a child of a node with `parent_visits` visits
+    fn get_score(&self, parent_visits: u64) -> f32;
+
+    // counts the results of rollouts from the perspective of the player who moved into this node
+    fn add_result(&mut self, wins_1: u64, wins_n1: u64) {
+        let mover = self.mover();
+        let (wins, losses) = self.results_mut();
+        if mover == 1 {
+            *wins += wins_1;
+            *losses += wins_n1;
+        }
+        else if mover == -1 {
+            *wins += wins_n1;
+            *losses += wins_1;
+        }
+    }
+
+    fn get_child_with_highest_score<R:Rng>(&self, rng: &mut R) -> Option<usize> {
+        if self.children().is_empty() {
+            return None;
+        }
+        let mut max_score = f32::NEG_INFINITY;
+        let mut max_index = 0usize;
+
+        for (index, node) in self.children().iter().enumerate(){
+            let score = node.get_score(self.visits());
+            if score > max_score {
+                max_index = index;
+                max_score = score;
+            }
+        }
+
+        // if some nodes were not visited yet, select random
+        if max_score == f32::INFINITY {
+            let not_visited = self.children().iter().enumerate().filter_map(|(i, n)| if ! n.created_children() {Some(i)} else {None});
+            return not_visited.choose(rng);
+        }
+
+        return Some(max_index);
+    }
+
+    fn get_most_visited_child(&self) -> Option<&Self> {
+        let mut most_visits = 0;
+        let mut most_visited : Option<&Self> = None;
+
+        for child in self.children().iter() {
+            if child.visits() > most_visits {
+                most_visits = child.visits();
+                most_visited = Some(child);
+            }
+        }
+
+        return most_visited;
+    }
 }
 
 #[derive(Debug,PartialEq, Clone)]
 pub struct Node<T:GeneralGame> {
     pub game: T,
-    pub player: i8,
+    // the player who made the move leading to this node, the statistics are from their perspective
+    pub mover: i8,
     pub visits: u64,
     pub wins: u64,
     pub losses: u64,
     pub children: Vec<Node<T>>,
     created_children: bool,
-    pub move_index: usize
+    // the move leading to this node, None for the root
+    pub move_index: Option<T::Move>,
+    // relative weight of the exploration term, 1.0 is neutral
+    pub prior: f32,
+    // the exploration constant of `uct_score`, inherited by the children
+    pub exploration: f32
+}
+
+impl<T:GeneralGame> SearchNode for Node<T> {
+    fn mover(&self) -> i8 {
+        return self.mover;
+    }
+    fn visits(&self) -> u64 {
+        return self.visits;
+    }
+    fn results_mut(&mut self) -> (&mut u64, &mut u64) {
+        return (&mut self.wins, &mut self.losses);
+    }
+    fn children(&self) -> &[Node<T>] {
+        return &self.children;
+    }
+    fn created_children(&self) -> bool {
+        return self.created_children;
+    }
+    fn get_score(&self, parent_visits: u64) -> f32 {
+        return uct_score(self.wins, self.losses, self.visits, parent_visits, self.prior, self.exploration);
+    }
 }
 
 impl<T:GeneralGame> Node<T> {
-    pub fn new(game : T, player: i8, move_index : usize) -> Node<T>{
-        return Node {game, player: player, visits: 0, wins: 0, losses: 0, children: Vec::new(), created_children: false, move_index: move_index};
+    pub fn new(game : T, move_index : Option<T::Move>) -> Node<T>{
+        let mover = -game.current_player();
+        return Node {game, mover, visits: 0, wins: 0, losses: 0, children: Vec::new(), created_children: false, move_index, prior: 1.0, exploration: UPPER_BOUND_CONSTANT};
     }
 
-    pub fn rollout(&self, rng: &mut ThreadRng) -> i8 {
+    pub fn rollout<R:Rng>(&self, rng: &mut R) -> i8 {
         let mut current_game = self.game.clone();
-        let mut current_player = self.player;
 
+        return Self::play_randomly(&mut current_game, usize::MAX, rng).unwrap();
+    }
+
+    // Plays at most `plies` random moves, returns the result if the game has finished
+    fn play_randomly<R:Rng>(game: &mut T, plies: usize, rng: &mut R) -> Option<i8> {
+        let mut ply = 0usize;
         loop {
-            let score = current_game.get_score();
+            let score = game.get_score();
             if score != 0 {
-                return score;
+                return Some(score);
+            }
+
+            let available = game.get_available();
+
+            if available.is_empty() {
+                return Some(0);
+            }
+
+            if ply == plies {
+                return None;
             }
+            ply += 1;
 
-            let available = current_game.get_available();
+            let index = available.choose(rng).unwrap().clone();
+            game.update(index);
+        }
+    }
+
+    fn sample_evaluation<R:Rng>(evaluator: &dyn Evaluator<T>, game: &T, rng: &mut R) -> i8 {
+        return sample_result(evaluator.evaluate(game), game.current_player(), rng);
+    }
 
-            if available.len() == 0 {
-                return 0;
+    pub fn simulate<R:Rng>(&self, playout: &Playout<T>, rng: &mut R) -> i8 {
+        match playout {
+            Playout::Random => {
+                return self.rollout(rng);
             }
+            Playout::Evaluate(evaluator) => {
+                return Self::simulate_truncated(&self.game, *evaluator, 0, rng);
+            }
+            Playout::Truncated(evaluator, plies) => {
+                return Self::simulate_truncated(&self.game, *evaluator, *plies, rng);
+            }
+        }
+    }
 
-            let index = *available.choose(rng).unwrap();
-            current_game.update(index, current_player);
-            current_player *= -1;
+    fn simulate_truncated<R:Rng>(game: &T, evaluator: &dyn Evaluator<T>, plies: usize, rng: &mut R) -> i8 {
+        let mut current_game = game.clone();
+
+        if let Some(score) = Self::play_randomly(&mut current_game, plies, rng) {
+            return score;
         }
+        return Self::sample_evaluation(evaluator, &current_game, rng);
     }
 
     pub fn create_children(&mut self){
@@ -62,70 +336,43 @@ impl<T:GeneralGame> Node<T> {
         let available = self.game.get_available();
 
         for index in available{
-            let mut child = Node::new(self.game.clone(), -self.player, index);
-            child.game.update(index, self.player);
+            let mut child = Node::new(self.game.clone(), Some(index.clone()));
+            child.game.update(index);
+            child.mover = self.game.current_player();
+            child.exploration = self.exploration;
             self.children.push(child);
         }
     }
 
-    pub fn get_score(&self, parent_visits: u64) -> f32 {
-        const UPPER_BOUND_CONSTANT : f32 = 1.4142*2.;
-
-        if self.visits == 0 {
-            return f32::INFINITY;
-        }
-
-        let fwins = (self.wins as f32)  - (self.losses as f32);
-        let fvisits = self.visits as f32;
-        let fparent_visits = parent_visits as f32;
-
-        return (fwins)/(fvisits) + UPPER_BOUND_CONSTANT * (fparent_visits.ln() / fvisits).sqrt();
+    pub fn propagate<R:Rng>(&mut self, rollouts: u64, rng: &mut R) -> (u64, u64){
+        return self.propagate_with(rollouts, &Playout::Random, rng);
     }
 
-    pub fn get_child_with_highest_score(&self, rng: &mut ThreadRng) -> Option<usize> {
-        if self.children.len() == 0{
-            return None;
+    pub fn propagate_with<R:Rng>(&mut self, rollouts: u64, playout: &Playout<T>, rng: &mut R) -> (u64, u64){
+        match self.try_propagate_with(rollouts, playout, rng) {
+            Ok(result) => return result,
+            Err(err) => panic!("{}", err)
         }
-        let mut max_score = f32::NEG_INFINITY;
-        let mut max_index = 0usize;
-
-        for (index, node) in self.children.iter().enumerate(){
-            let score = node.get_score(self.visits);
-            if score > max_score {
-                max_index = index;
-                max_score = score;
-            }
-        }
-
-        // if some nodes were not visited yet, select random
-        if max_score == f32::INFINITY {
-            let not_visited = self.children.iter().enumerate().filter_map(|(i, n)| if ! n.created_children {Some(i)} else {None});
-            return not_visited.choose(rng);
-        }
-
-        return Some(max_index);
     }
 
-    // this is not tested, make sure to test this manually!
-    pub fn propagate(&mut self, rollouts: u64, rng: &mut ThreadRng) -> (u64, u64){
-        // returns (visits, player1 wins, player-1 wins)
+    // Returns an error if the game reports an invalid score, the statistics of the tree are not reliable after that.
+    pub fn try_propagate_with<R:Rng>(&mut self, rollouts: u64, playout: &Playout<T>, rng: &mut R) -> Result<(u64, u64), GameError<T::Move>> {
+        // returns (player1 wins, player-1 wins)
         self.visits += rollouts;
 
         // if someone has already won, just return the winner
         let score = self.game.get_score();
         if score != 0 {
             if score == 1 {
-                if self.player == -1 { self.wins += rollouts; }
-                else { self.losses += rollouts; }
-                return (rollouts, 0);
+                self.add_result(rollouts, 0);
+                return Ok((rollouts, 0));
             }
             else if score == -1 {
-                if self.player == 1 { self.wins += rollouts; }
-                else { self.losses += rollouts; }
-                return (0, rollouts)
+                self.add_result(0, rollouts);
+                return Ok((0, rollouts));
             }
             else {
-                panic!("Invalid score");
+                return Err(GameError::InvalidScore(score));
             }
         }
 
@@ -136,7 +383,7 @@ impl<T:GeneralGame> Node<T> {
             self.create_children();
 
             for _ in 0..rollouts {
-                let res = self.rollout(rng);
+                let res = self.simulate(playout, rng);
                 
                 if res == 1{
                     wins_1 += 1;
@@ -144,47 +391,114 @@ impl<T:GeneralGame> Node<T> {
                 else if res == -1 {
                     wins_n1 += 1;
                 }
+                else if res != 0 {
+                    return Err(GameError::InvalidScore(res));
+                }
             }
         }
         // recursively call next children with highest score
         else {
             let next = self.get_child_with_highest_score(rng);
             if let Some(next_node_index) = next {
-                (wins_1, wins_n1) = self.children[next_node_index].propagate(rollouts, rng);
+                (wins_1, wins_n1) = self.children[next_node_index].try_propagate_with(rollouts, playout, rng)?;
             }
         }
 
         // update self
-        if self.player == -1 {
-            self.wins += wins_1;
-            self.losses += wins_n1;
+        self.add_result(wins_1, wins_n1);
+
+        return Ok((wins_1, wins_n1));
+    }
+
+    // Mixes Dirichlet noise into the priors of the children of this node, should only be used on the search root.
+    // The priors keep mean 1, so weight 0 leaves the search unchanged.
+    // Needs alpha > 0 and 0 <= weight <= 1, otherwise the priors are left as they are.
+    pub fn add_dirichlet_noise<R:Rng>(&mut self, alpha: f32, weight: f32, rng: &mut R) {
+        if alpha.is_nan() || alpha <= 0. || !(0. ..=1.).contains(&weight) {
+            return;
+        }
+        if ! self.created_children {
+            self.create_children();
         }
-        else if self.player == 1 {
-            self.wins += wins_n1;
-            self.losses += wins_1;
+        if self.children.is_empty() {
+            return;
         }
 
-        return (wins_1, wins_n1);
+        let gamma = Gamma::new(alpha, 1.0).unwrap();
+        let samples = Vec::from_iter(self.children.iter().map(|_| gamma.sample(rng)));
+        let sum = samples.iter().sum::<f32>().max(f32::MIN_POSITIVE);
+
+        let count = self.children.len() as f32;
+        for (child, sample) in self.children.iter_mut().zip(samples) {
+            child.prior = (1. - weight) + weight * count * sample / sum;
+        }
     }
 
-    pub fn get_most_visited_child(&self) -> Option<&Node<T>> {
-        let mut most_visits = 0;
-        let mut most_visited : Option<&Node<T>> = None;
+    pub fn predict(&mut self, iters: usize, rollouts: u64) {
+        self.predict_with(iters, rollouts, &Playout::Random);
+    }
 
-        for child in self.children.iter() {
-            if child.visits > most_visits {
-                most_visits = child.visits;
-                most_visited = Some(child);
-            }
+    pub fn predict_with(&mut self, iters: usize, rollouts: u64, playout: &Playout<T>) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..iters {
+            self.propagate_with(rollouts, playout, &mut rng);
         }
-
-        return most_visited;
     }
 
-    pub fn predict(&mut self, iters: usize, rollouts: u64) {
+    pub fn try_predict(&mut self, iters: usize, rollouts: u64, playout: &Playout<T>) -> Result<(), GameError<T::Move>> {
         let mut rng = rand::thread_rng();
         for _ in 0..iters {
-            self.propagate(rollouts, &mut rng);
+            self.try_propagate_with(rollouts, playout, &mut rng)?;
+        }
+        return Ok(());
+    }
+}
+
+impl<T:Symmetries> Node<T> {
+    // Keeps one child for every group of children reaching symmetric positions and adds up
+    // the statistics and priors of the group, so the search budget is not split between equivalent moves.
+    // The subtrees of the group are merged into the kept child, so this also works after a search.
+    pub fn merge_symmetric_children(&mut self) {
+        if ! self.created_children {
+            self.create_children();
+        }
+
+        let mut keys : Vec<T::Key> = Vec::new();
+        let mut merged : Vec<Node<T>> = Vec::new();
+        for child in self.children.drain(..) {
+            let key = child.game.canonical_key().0;
+            match keys.iter().position(|k| *k == key) {
+                Some(i) => {
+                    merged[i].prior += child.prior;
+                    merged[i].absorb(child);
+                }
+                None => {
+                    keys.push(key);
+                    merged.push(child);
+                }
+            }
+        }
+        self.children = merged;
+    }
+
+    // Adds the statistics of `other`, whose position is symmetric to this one, and of its subtree move by move
+    fn absorb(&mut self, other: Node<T>) {
+        self.visits += other.visits;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        if ! other.created_children {
+            return;
+        }
+        if ! self.created_children {
+            self.create_children();
+        }
+
+        let key = self.game.key();
+        let symmetry = (0..other.game.symmetry_count()).find(|&symmetry| other.game.transform(symmetry).key() == key).unwrap();
+        for child in other.children {
+            let index = other.game.transform_move(symmetry, child.move_index.as_ref().unwrap());
+            let target = self.children.iter_mut().find(|ch| ch.move_index.as_ref() == Some(&index)).unwrap();
+            target.absorb(child);
         }
     }
 }
@@ -192,29 +506,31 @@ impl<T:GeneralGame> Node<T> {
 
 #[cfg(test)]
 use super::tictactoe::TicTacToe;
+#[cfg(test)]
+use super::connect4::Connect4;
 #[test]
 fn test_node_new(){
     let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    let node = Node::new(tictactoe, -1, 0);
+    let node = Node::new(tictactoe, None);
 
     let game = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    assert_eq!(node, Node {game: game, player: -1, visits: 0, wins: 0, losses: 0, children: Vec::<Node<TicTacToe>>::new(), created_children: false, move_index: 0})
+    assert_eq!(node, Node {game, mover: 1, visits: 0, wins: 0, losses: 0, children: Vec::<Node<TicTacToe>>::new(), created_children: false, move_index: None, prior: 1.0, exploration: UPPER_BOUND_CONSTANT})
 }
 
 #[test]
 fn test_node_rollout(){
     let mut rng = rand::thread_rng();
 
-    let tictactoe = TicTacToe::from_string("XX.\nOOX\nOXO").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
-
+    let tictactoe = TicTacToe::from_string("XX.\nOOX\nOXX").unwrap();
+    let node = Node::new(tictactoe, None);
     assert_eq!(node.rollout(&mut rng), -1);
 
-    node.player = 1;
+    let tictactoe = TicTacToe::from_string("XX.\nOOX\nOXO").unwrap();
+    let node = Node::new(tictactoe, None);
     assert_eq!(node.rollout(&mut rng), 1);
 
     let tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
-    let node = Node::new(tictactoe, -1, 0);
+    let node = Node::new(tictactoe, None);
     const MAX_ITER:usize = 10000;
     let mut iter = 0usize;
     let (mut player_1, mut player_2, mut draw) = (false, false, false);
@@ -234,7 +550,7 @@ fn test_node_rollout(){
 #[test]
 fn test_node_create_children(){
     let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
+    let mut node = Node::new(tictactoe, None);
 
     node.create_children();
     assert_eq!(node.children.len(), 4);
@@ -244,7 +560,7 @@ fn test_node_create_children(){
 #[test]
 fn test_node_score(){
     let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
+    let mut node = Node::new(tictactoe, None);
 
     assert_eq!(node.get_score(1), f32::INFINITY);
     node.visits = 1;
@@ -259,6 +575,12 @@ fn test_node_score(){
     node.wins = 1;
     node.losses = 2;
     assert!((node.get_score(10) - 1.7194).abs() < 0.0001);
+
+    // without exploration only the results count, children inherit the constant
+    node.exploration = 0.;
+    assert!((node.get_score(10) + 0.2).abs() < 0.0001);
+    node.create_children();
+    assert!(node.children.iter().all(|child| child.exploration == 0.));
 }
 
 #[test]
@@ -266,7 +588,7 @@ fn test_node_next_maxscore(){
     let mut rng = rand::thread_rng();
 
     let tictactoe = TicTacToe::from_string("X.O\nOXO\nXX.").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
+    let mut node = Node::new(tictactoe, None);
 
     assert_eq!(node.get_child_with_highest_score(&mut rng), None);
 
@@ -303,4 +625,285 @@ fn test_node_next_maxscore(){
     node.visits = 21;
 
     assert_eq!(node.get_child_with_highest_score(&mut rng), Some(0));
+}
+
+#[test]
+fn test_node_dirichlet_noise(){
+    let mut rng = rand::thread_rng();
+
+    let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
+    let mut node = Node::new(tictactoe, None);
+
+    node.add_dirichlet_noise(0.3, 0.25, &mut rng);
+    assert_eq!(node.children.len(), 4);
+    let sum = node.children.iter().map(|ch| ch.prior).sum::<f32>();
+    assert!((sum - 4.).abs() < 0.001);
+    assert!(node.children.iter().all(|ch| ch.prior >= 0.75));
+
+    // noise is only applied at the root
+    node.predict(50, 1);
+    for child in node.children.iter() {
+        assert!(child.children.iter().all(|grandchild| grandchild.prior == 1.0));
+    }
+
+    let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
+    let mut node = Node::new(tictactoe, None);
+    node.add_dirichlet_noise(0.3, 0., &mut rng);
+    assert!(node.children.iter().all(|ch| ch.prior == 1.0));
+
+    // invalid settings are ignored instead of panicking
+    for (alpha, weight) in [(0., 0.25), (-1., 0.25), (f32::NAN, 0.25), (0.3, -0.1), (0.3, 1.5), (0.3, f32::NAN)] {
+        node.add_dirichlet_noise(alpha, weight, &mut rng);
+        assert!(node.children.iter().all(|ch| ch.prior == 1.0));
+    }
+}
+
+#[test]
+fn test_node_dirichlet_noise_first_moves(){
+    let mut rng = rand::thread_rng();
+
+    let mut first_moves = std::collections::HashSet::new();
+    for _ in 0..30 {
+        let mut node = Node::new(Connect4::empty(), None);
+        node.add_dirichlet_noise(0.3, 0.75, &mut rng);
+        node.predict(100, 1);
+        first_moves.insert(node.get_most_visited_child().unwrap().move_index.unwrap());
+    }
+
+    assert!(first_moves.len() >= 3, "first moves: {:?}", first_moves);
+}
+
+#[cfg(test)]
+struct ConstantEvaluator(f32);
+#[cfg(test)]
+impl<T:GeneralGame> Evaluator<T> for ConstantEvaluator {
+    fn evaluate(&self, _game: &T) -> f32 {
+        return self.0;
+    }
+}
+
+#[test]
+fn test_node_simulate(){
+    let mut rng = rand::thread_rng();
+
+    let tictactoe = TicTacToe::from_string("...\n.X.\n...").unwrap();
+    let node = Node::new(tictactoe, None);
+
+    // the side to move always wins according to the evaluator
+    let evaluator = ConstantEvaluator(1.);
+    for _ in 0..20 {
+        assert_eq!(node.simulate(&Playout::Evaluate(&evaluator), &mut rng), -1);
+        assert_eq!(node.simulate(&Playout::Truncated(&evaluator, 0), &mut rng), -1);
+    }
+
+    // after one random ply the other player is to move
+    for _ in 0..20 {
+        assert_eq!(node.simulate(&Playout::Truncated(&evaluator, 1), &mut rng), 1);
+    }
+
+    // finished games are not evaluated
+    let tictactoe = TicTacToe::from_string("XX.\nOOX\nOXO").unwrap();
+    let node = Node::new(tictactoe, None);
+    let evaluator = ConstantEvaluator(0.);
+    for _ in 0..20 {
+        assert_eq!(node.simulate(&Playout::Truncated(&evaluator, 1), &mut rng), 1);
+        assert_eq!(node.simulate(&Playout::Truncated(&evaluator, 100), &mut rng), 1);
+    }
+}
+
+#[test]
+fn test_node_predict_with_evaluator(){
+    // X to move, (0,2) wins immediately
+    let tictactoe = TicTacToe::from_string("XX.\nOO.\n...").unwrap();
+    let evaluator = ConstantEvaluator(0.5);
+
+    for playout in [Playout::Evaluate(&evaluator), Playout::Truncated(&evaluator, 2)] {
+        let mut node = Node::new(tictactoe.clone(), None);
+        node.predict_with(200, 1, &playout);
+        assert_eq!(node.visits, 200);
+        assert_eq!(node.get_most_visited_child().unwrap().move_index, Some((0, 2)));
+    }
+}
+
+// a game of one move whose score is out of range afterwards
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+struct BrokenGame(bool);
+#[cfg(test)]
+impl IndexedGame for BrokenGame {
+    fn update(&mut self, _index:usize) {
+        self.0 = true;
+    }
+    fn get_score(&self) -> i8 {
+        return if self.0 {2} else {0};
+    }
+    fn get_available(&self) -> Vec<usize> {
+        return if self.0 {vec![]} else {vec![0]};
+    }
+    fn current_player(&self) -> i8 {
+        return if self.0 {-1} else {1};
+    }
+}
+
+#[test]
+fn test_game_try_update(){
+    let mut tictactoe = TicTacToe::from_string("X..\n.O.\n...").unwrap();
+    assert_eq!(tictactoe.try_update((1, 1)), Err(GameError::IllegalMove((1, 1))));
+    assert_eq!(tictactoe.try_update((3, 0)), Err(GameError::IllegalMove((3, 0))));
+    assert_eq!(tictactoe, TicTacToe::from_string("X..\n.O.\n...").unwrap());
+
+    assert_eq!(tictactoe.try_update((0, 2)), Ok(()));
+    assert_eq!(tictactoe, TicTacToe::from_string("X.X\n.O.\n...").unwrap());
+
+    let mut tictactoe = TicTacToe::from_string("XXX\nOO.\n...").unwrap();
+    assert_eq!(tictactoe.try_update((1, 2)), Err(GameError::GameOver));
+
+    let mut broken = BrokenGame(true);
+    assert_eq!(broken.try_update(0), Err(GameError::InvalidScore(2)));
+}
+
+#[test]
+fn test_node_try_propagate(){
+    let mut rng = rand::thread_rng();
+
+    let mut node = Node::new(BrokenGame(false), None);
+    assert_eq!(node.try_propagate_with(1, &Playout::Random, &mut rng), Err(GameError::InvalidScore(2)));
+
+    let mut node = Node::new(BrokenGame(false), None);
+    node.create_children();
+    assert_eq!(node.try_predict(10, 1, &Playout::Random), Err(GameError::InvalidScore(2)));
+
+    let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
+    let mut node = Node::new(tictactoe, None);
+    assert_eq!(node.try_predict(10, 1, &Playout::Random), Ok(()));
+    assert_eq!(node.visits, 10);
+}
+
+// Nim with a single pile, a move takes one or two stones, whoever takes the last stone wins.
+// With `extra_turn` taking two stones keeps the turn.
+#[cfg(test)]
+#[derive(Clone)]
+struct Nim {
+    stones: usize,
+    last_player: i8,
+    last_take: usize,
+    extra_turn: bool
+}
+#[cfg(test)]
+impl Nim {
+    fn new(stones: usize, extra_turn: bool) -> Nim {
+        return Nim { stones, last_player: -1, last_take: 0, extra_turn };
+    }
+}
+#[cfg(test)]
+impl IndexedGame for Nim {
+    fn update(&mut self, index:usize) {
+        self.last_player = IndexedGame::current_player(self);
+        self.last_take = index;
+        self.stones -= index;
+    }
+    fn get_score(&self) -> i8 {
+        return if self.stones == 0 {self.last_player} else {0};
+    }
+    fn get_available(&self) -> Vec<usize> {
+        return Vec::from_iter((1..=2).filter(|&take| take <= self.stones));
+    }
+    fn current_player(&self) -> i8 {
+        return if self.extra_turn && self.last_take == 2 {self.last_player} else {-self.last_player};
+    }
+}
+
+#[test]
+fn test_indexed_game_adapter(){
+    // taking one stone leaves a multiple of three, which is lost for the opponent
+    let mut node = Node::new(Nim::new(7, false), None);
+    node.predict(2000, 1);
+    assert_eq!(node.get_most_visited_child().unwrap().move_index, Some(1));
+
+    let mut nim = Nim::new(1, false);
+    assert_eq!(nim.try_update(2), Err(GameError::IllegalMove(2)));
+    assert_eq!(nim.try_update(1), Ok(()));
+    assert_eq!(GeneralGame::get_score(&nim), 1);
+}
+
+#[test]
+fn test_node_extra_turn(){
+    // taking two keeps the turn, so the last stone can be taken as well
+    let mut node = Node::new(Nim::new(3, true), None);
+    node.predict(500, 1);
+    let best = node.get_most_visited_child().unwrap();
+    assert_eq!(best.move_index, Some(2));
+    assert_eq!(best.mover, 1);
+    assert_eq!(GeneralGame::current_player(&best.game), 1);
+    assert_eq!(best.children[0].mover, 1);
+    assert!(best.wins > best.losses);
+
+    // without the extra turn the opponent takes the last two stones
+    let mut node = Node::new(Nim::new(3, false), None);
+    node.predict(500, 1);
+    assert!(node.children.iter().all(|ch| ch.wins < ch.losses));
+}
+
+#[test]
+fn test_node_merge_symmetric_children(){
+    // corner, edge and centre
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), None);
+    node.merge_symmetric_children();
+    assert_eq!(node.children.len(), 3);
+
+    let mut node = Node::new(Connect4::empty(), None);
+    node.predict(300, 1);
+    let (visits, wins) = (node.children.iter().map(|ch| ch.visits).sum::<u64>(), node.children.iter().map(|ch| ch.wins).sum::<u64>());
+    node.merge_symmetric_children();
+    assert_eq!(Vec::from_iter(node.children.iter().map(|ch| ch.move_index.unwrap())), [0, 1, 2]);
+    assert_eq!(node.children.iter().map(|ch| ch.visits).sum::<u64>(), visits);
+    assert_eq!(node.children.iter().map(|ch| ch.wins).sum::<u64>(), wins);
+
+    // the search continues on the merged children
+    node.predict(100, 1);
+    assert_eq!(node.visits, 400);
+    assert_eq!(node.children.len(), 3);
+}
+
+// (visits, wins, losses) of all nodes `depth` moves below `node`
+#[cfg(test)]
+fn depth_totals<T:GeneralGame>(node: &Node<T>, depth: usize) -> (u64, u64, u64) {
+    if depth == 0 {
+        return (node.visits, node.wins, node.losses);
+    }
+    return node.children.iter().map(|ch| depth_totals(ch, depth - 1)).fold((0, 0, 0), |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2));
+}
+
+// an expanded node keeps the rollouts made when it was expanded and passes all other visits on to its children
+#[cfg(test)]
+fn assert_consistent<T:GeneralGame>(node: &Node<T>) {
+    if ! node.children.is_empty() {
+        let child_visits = node.children.iter().map(|ch| ch.visits).sum::<u64>();
+        assert!(child_visits < node.visits, "{} visits, {} in the children", node.visits, child_visits);
+    }
+    for child in node.children.iter() {
+        assert_consistent(child);
+    }
+}
+
+#[test]
+fn test_node_merge_symmetric_subtrees(){
+    // merging after a search keeps the statistics below the merged children
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), None);
+    node.predict(2000, 1);
+    let totals = Vec::from_iter((0..4).map(|depth| depth_totals(&node, depth)));
+    node.merge_symmetric_children();
+
+    assert_eq!(node.children.len(), 3);
+    assert_eq!(Vec::from_iter((0..4).map(|depth| depth_totals(&node, depth))), totals);
+    assert_consistent(&node);
+
+    // the priors of every group are added up: four corners, four edges and the centre
+    let mut priors = Vec::from_iter(node.children.iter().map(|ch| ch.prior));
+    priors.sort_by(f32::total_cmp);
+    assert_eq!(priors, [1., 4., 4.]);
+
+    node.predict(500, 1);
+    assert_eq!(node.visits, 2500);
+    assert_consistent(&node);
 }
\ No newline at end of file
@@ -0,0 +1,322 @@
+use std::fmt;
+use super::mcts::{GeneralGame, Reversible, Symmetries};
+
+// Two players take turns placing tokens on an M x N board (M rows, N columns),
+// whoever first gets K in a row horizontally, vertically or diagonally wins.
+// Tic-tac-toe is MNKGame<3, 3, 3>, gomoku MNKGame<15, 15, 5>.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MNKGame<const M: usize, const N: usize, const K: usize> {
+    pub board: [[i8; N]; M],
+    // kept up to date by `update`, so that no move has to scan the whole board
+    score: i8,
+    player: i8
+}
+
+// the four directions of a line, each line is followed both ways, also used by `ConnectN`
+pub const DIRECTIONS : [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+// the number of tokens of the same player in a line through (row, col) in direction (drow, dcol)
+fn line_length<const M: usize, const N: usize>(board: &[[i8; N]; M], row: usize, col: usize, (drow, dcol): (isize, isize)) -> usize {
+    let target = board[row][col];
+    let mut length = 1;
+    for sign in [-1isize, 1] {
+        let (mut r, mut c) = (row as isize + sign*drow, col as isize + sign*dcol);
+        while r >= 0 && r < M as isize && c >= 0 && c < N as isize && board[r as usize][c as usize] == target {
+            length += 1;
+            r += sign*drow;
+            c += sign*dcol;
+        }
+    }
+    return length;
+}
+
+// whether the token on (row, col) is part of K in a row
+pub fn completes_line<const M: usize, const N: usize>(board: &[[i8; N]; M], row: usize, col: usize, k: usize) -> bool {
+    return DIRECTIONS.iter().any(|&direction| line_length(board, row, col, direction) >= k);
+}
+
+impl<const M: usize, const N: usize, const K: usize> MNKGame<M, N, K> {
+    pub fn empty() -> MNKGame<M, N, K> {
+        return MNKGame { board: [[0; N]; M], score: 0, player: 1 };
+    }
+
+    pub fn from_board(board: [[i8; N]; M]) -> MNKGame<M, N, K> {
+        let mut game = MNKGame { board, score: 0, player: 1 };
+
+        // X always starts, so the player to move follows from the number of tokens
+        let count_1 = board.iter().flatten().filter(|&&cell| cell == 1).count();
+        let count_n1 = board.iter().flatten().filter(|&&cell| cell == -1).count();
+        game.player = if count_1 > count_n1 {-1} else {1};
+
+        'targets: for target in [-1i8, 1i8] {
+            for (row, cells) in board.iter().enumerate() {
+                for (col, &cell) in cells.iter().enumerate() {
+                    if cell == target && game.completes_line(row, col) {
+                        game.score = target;
+                        break 'targets;
+                    }
+                }
+            }
+        }
+        return game;
+    }
+
+    fn completes_line(&self, row: usize, col: usize) -> bool {
+        return completes_line(&self.board, row, col, K);
+    }
+
+    pub fn get_score(&self) -> i8 {
+        return self.score;
+    }
+
+    pub fn get_available(&self) -> Vec<(usize, usize)> {
+        let mut res : Vec<(usize, usize)> = Vec::new();
+        for i in 0..M {
+            for j in 0..N {
+                if self.board[i][j] == 0{
+                    res.push((i,j));
+                }
+            }
+        }
+
+        return res;
+    }
+
+    pub fn from_string(val : &str) -> Option<MNKGame<M, N, K>> {
+        let mut board = [[0i8; N]; M];
+
+        // spaces are ignored, so the output of `Display` can be read back
+        for (i,s) in val.chars().filter(|&s| s != ' ').enumerate(){
+            if (i+1)%(N+1)==0 {
+                if s != '\n' && s != '\r'{
+                    return None;
+                }
+            }
+            else {
+                let x = i%(N+1);
+                let y = i/(N+1);
+                if y >= M {
+                    return None;
+                }
+                board[y][x] = match s {
+                    'X' => 1i8,
+                    'O' => -1i8,
+                    '.' => 0i8,
+                    _ => return None
+                };
+            }
+        }
+
+        return Some(MNKGame::from_board(board))
+    }
+
+    pub fn current_player(&self) -> i8 {
+        return self.player;
+    }
+
+    pub fn update(&mut self, indeces : (usize, usize)) {
+        let (row, col) = indeces;
+        debug_assert!(self.board[row][col] == 0, "Square ({}, {}) is taken.", row, col);
+        self.board[row][col] = self.player;
+        // only lines through the new token can be new
+        if self.score == 0 && self.completes_line(row, col) {
+            self.score = self.player;
+        }
+        self.player = -self.player;
+    }
+}
+
+impl<const M: usize, const N: usize, const K: usize> GeneralGame for MNKGame<M, N, K> {
+    // (row, column)
+    type Move = (usize, usize);
+
+    fn get_score(&self) -> i8 {
+        return self.get_score();
+    }
+    fn update(&mut self, indeces:(usize, usize)) {
+        self.update(indeces);
+    }
+
+    fn get_available(&self) -> Vec<(usize, usize)> {
+        return self.get_available();
+    }
+
+    fn current_player(&self) -> i8 {
+        return self.current_player();
+    }
+}
+
+impl<const M: usize, const N: usize, const K: usize> Reversible for MNKGame<M, N, K> {
+    // the square that was taken and the score before the move
+    type Undo = ((usize, usize), i8);
+
+    fn update_with_undo(&mut self, indeces:(usize, usize)) -> ((usize, usize), i8) {
+        let score = self.score;
+        self.update(indeces);
+        return (indeces, score);
+    }
+
+    fn undo(&mut self, ((row, col), score): ((usize, usize), i8)) {
+        self.player = self.board[row][col];
+        self.board[row][col] = 0;
+        self.score = score;
+    }
+}
+
+impl<const M: usize, const N: usize, const K: usize> MNKGame<M, N, K> {
+    // Square boards: symmetries 0..4 rotate the board clockwise by 90 degrees that many times,
+    // symmetries 4..8 mirror the columns first and then rotate.
+    // Other boards can only be turned by 180 degrees, their symmetries 0..4 are 0, 2, 4 and 6 of the square ones.
+    fn map_square(symmetry: usize, (row, col): (usize, usize)) -> (usize, usize) {
+        let symmetry = if M == N {symmetry} else {2*symmetry};
+        let (mut row, mut col) = if symmetry >= 4 {(row, N-1-col)} else {(row, col)};
+        if symmetry%4 == 2 {
+            return (M-1-row, N-1-col);
+        }
+        for _ in 0..symmetry%4 {
+            (row, col) = (col, N-1-row);
+        }
+        return (row, col);
+    }
+}
+
+impl<const M: usize, const N: usize, const K: usize> Symmetries for MNKGame<M, N, K> {
+    type Key = [[i8; N]; M];
+
+    fn symmetry_count(&self) -> usize {
+        return if M == N {8} else {4};
+    }
+
+    fn transform(&self, symmetry: usize) -> MNKGame<M, N, K> {
+        let mut transformed = MNKGame { board: [[0; N]; M], score: self.score, player: self.player };
+        for i in 0..M {
+            for j in 0..N {
+                let (row, col) = Self::map_square(symmetry, (i, j));
+                transformed.board[row][col] = self.board[i][j];
+            }
+        }
+        return transformed;
+    }
+
+    fn transform_move(&self, symmetry: usize, indeces: &(usize, usize)) -> (usize, usize) {
+        return Self::map_square(symmetry, *indeces);
+    }
+
+    // mirrored symmetries and the turn by 180 degrees are their own inverse
+    fn inverse(&self, symmetry: usize) -> usize {
+        return if M != N || symmetry >= 4 {symmetry} else {(4 - symmetry) % 4};
+    }
+
+    fn key(&self) -> [[i8; N]; M] {
+        return self.board;
+    }
+}
+
+impl<const M: usize, const N: usize, const K: usize> fmt::Display for MNKGame<M, N, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..M {
+            for j in 0..N {
+                write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
+            }
+            writeln!(f).unwrap();
+        }
+        write!(f, "")
+    }
+}
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+#[cfg(test)]
+type Gomoku = MNKGame<15, 15, 5>;
+
+#[test]
+fn test_mnk_gomoku(){
+    let mut gomoku = Gomoku::empty();
+    assert_eq!(gomoku.get_available().len(), 225);
+
+    // four in a row are not enough, the fifth wins in the middle of the line
+    for (x, o) in [((7, 3), (0, 0)), ((7, 4), (0, 1)), ((7, 6), (0, 2)), ((7, 7), (0, 3))] {
+        gomoku.update(x);
+        gomoku.update(o);
+        assert_eq!(gomoku.get_score(), 0);
+    }
+    gomoku.update((7, 5));
+    assert_eq!(gomoku.get_score(), 1);
+
+    let diagonal = Gomoku::from_string(&(0..15).map(|row| {
+        String::from_iter((0..15).map(|col| if row >= 10 && row + col == 24 {'O'} else if row == 0 && col < 4 {'X'} else {'.'})) + "\n"
+    }).collect::<String>()).unwrap();
+    assert_eq!(diagonal.get_score(), -1);
+    assert_eq!(diagonal.current_player(), 1);
+}
+
+#[test]
+fn test_mnk_incremental_score(){
+    // the score after every move agrees with a scan of the whole board
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..50 {
+        let mut game = MNKGame::<7, 9, 4>::empty();
+        let mut history = Vec::new();
+        while game.get_score() == 0 && !game.get_available().is_empty() {
+            let index = *game.get_available().choose(&mut rng).unwrap();
+            let before = game.clone();
+            let undo = game.update_with_undo(index);
+            assert_eq!(game, MNKGame::from_board(game.board));
+            history.push((before, undo));
+        }
+        while let Some((before, undo)) = history.pop() {
+            game.undo(undo);
+            assert_eq!(game, before);
+        }
+    }
+
+    // undo of a move made after the game was won
+    let mut won = MNKGame::<3, 3, 3>::from_board([[1, 1, 1], [-1, -1, 0], [0, 0, 0]]);
+    assert_eq!(won.get_score(), 1);
+    let before = won.clone();
+    let undo = won.update_with_undo((2, 2));
+    won.undo(undo);
+    assert_eq!(won, before);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "is taken")]
+fn test_mnk_taken_square(){
+    let mut game = MNKGame::<3, 3, 3>::empty();
+    game.update((1, 1));
+    game.update((1, 1));
+}
+
+#[test]
+fn test_mnk_from_string(){
+    let game = MNKGame::<2, 4, 3>::from_string("X.O.\n.XO.\n").unwrap();
+    assert_eq!(game.board, [[1, 0, -1, 0], [0, 1, -1, 0]]);
+    assert_eq!(game.to_string(), "X . O . \n. X O . \n");
+    assert_eq!(MNKGame::<2, 4, 3>::from_string(&game.to_string()), Some(game));
+
+    assert_eq!(MNKGame::<2, 4, 3>::from_string("X.O.\n.XO.\n....\n"), None);
+    assert_eq!(MNKGame::<2, 4, 3>::from_string("X.O\n.XO.\n"), None);
+    assert_eq!(MNKGame::<2, 4, 3>::from_string("X.A.\n"), None);
+}
+
+#[test]
+fn test_mnk_rectangular_symmetries(){
+    let game = MNKGame::<2, 3, 2>::from_string("X..\n.O.").unwrap();
+    assert_eq!(game.symmetry_count(), 4);
+
+    let transformed = Vec::from_iter((0..4).map(|symmetry| game.transform(symmetry)));
+    assert_eq!(transformed[1], MNKGame::from_string(".O.\n..X").unwrap());
+    assert_eq!(transformed[2], MNKGame::from_string("..X\n.O.").unwrap());
+    assert_eq!(transformed[3], MNKGame::from_string(".O.\nX..").unwrap());
+    for (symmetry, transformed) in transformed.iter().enumerate() {
+        assert_eq!(transformed.transform(game.inverse(symmetry)), game);
+        for index in game.get_available() {
+            let (mut played, mut played_transformed) = (game.clone(), transformed.clone());
+            played.update(index);
+            played_transformed.update(game.transform_move(symmetry, &index));
+            assert_eq!(played.transform(symmetry), played_transformed);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use super::mcts::{GeneralGame, Evaluator};
+
+// Value of a won game, reduced by the number of plies so that faster wins are preferred.
+// Evaluations of unfinished positions stay within [-1, 1].
+pub const WIN_VALUE : f32 = 1000.;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegamaxResult<M> {
+    // value for the player to move
+    pub value: f32,
+    // the principal variation, empty if the game is already over
+    pub moves: Vec<M>,
+    // the depth of the last completed iteration
+    pub depth: usize,
+    pub nodes: u64
+}
+
+impl<M> NegamaxResult<M> {
+    pub fn best_move(&self) -> Option<&M> {
+        return self.moves.first();
+    }
+
+    // true if the value is a proven win or loss
+    pub fn is_decided(&self) -> bool {
+        return self.value.abs() > 1.;
+    }
+}
+
+// Where a node sits in the search: the remaining depth, the distance from the root and the alpha-beta window
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    depth: usize,
+    ply: usize,
+    alpha: f32,
+    beta: f32
+}
+
+impl Frame {
+    // the frame of a child, the window is negated if the turn passes to the opponent
+    fn child(&self, same_player: bool) -> Frame {
+        let (alpha, beta) = if same_player {(self.alpha, self.beta)} else {(-self.beta, -self.alpha)};
+        return Frame { depth: self.depth - 1, ply: self.ply + 1, alpha, beta };
+    }
+}
+
+// Depth limited negamax with alpha-beta pruning. Without an evaluator unfinished positions at the horizon count as draws.
+// Moves of the principal variation from the previous iteration are searched first,
+// then the killer moves, the last two moves that caused a cutoff at the same ply.
+pub struct Negamax<'a, T:GeneralGame> {
+    pub evaluator: Option<&'a dyn Evaluator<T>>,
+    // whether killer moves are tried early, switching it off only costs nodes
+    pub use_killers: bool,
+    nodes: u64,
+    // whether the last iteration stopped at the horizon anywhere
+    reached_horizon: bool,
+    // by ply, the most recent first
+    killers: Vec<[Option<T::Move>; 2]>
+}
+
+impl<'a, T:GeneralGame> Negamax<'a, T> {
+    pub fn new(evaluator: Option<&'a dyn Evaluator<T>>) -> Negamax<'a, T> {
+        return Negamax { evaluator, use_killers: true, nodes: 0, reached_horizon: false, killers: Vec::new() };
+    }
+
+    // Iterative deepening up to `max_depth` plies, stops early once the result is decided
+    pub fn search(&mut self, game: &T, max_depth: usize) -> NegamaxResult<T::Move> {
+        self.nodes = 0;
+        self.killers.clear();
+        let mut result = NegamaxResult { value: self.evaluate(game), moves: Vec::new(), depth: 0, nodes: 0 };
+
+        for depth in 1..=max_depth {
+            self.reached_horizon = false;
+            let mut moves = Vec::new();
+            let frame = Frame { depth, ply: 0, alpha: -f32::INFINITY, beta: f32::INFINITY };
+            let value = self.negamax(game, frame, &result.moves, &mut moves);
+            result = NegamaxResult { value, moves, depth, nodes: self.nodes };
+
+            // deeper iterations would repeat the same search
+            if result.is_decided() || ! self.reached_horizon {
+                break;
+            }
+        }
+
+        return result;
+    }
+
+    fn evaluate(&self, game: &T) -> f32 {
+        let score = game.get_score();
+        if score != 0 {
+            return if score == game.current_player() {WIN_VALUE} else {-WIN_VALUE};
+        }
+        return match self.evaluator {
+            Some(evaluator) => 2. * evaluator.evaluate(game) - 1.,
+            None => 0.
+        };
+    }
+
+    // moves the principal variation move and the killers of this ply to the front, in that order,
+    // returns whether the first move is the one of the principal variation
+    fn order_moves(&self, available: &mut Vec<T::Move>, pv: &[T::Move], ply: usize) -> bool {
+        let killers = if self.use_killers {self.killers.get(ply).cloned().unwrap_or([None, None])} else {[None, None]};
+        let mut front = 0;
+        let mut pv_first = false;
+        for (i, preferred) in pv.first().into_iter().chain(killers.iter().flatten()).enumerate() {
+            if let Some(position) = available[front..].iter().position(|index| index == preferred) {
+                let index = available.remove(front + position);
+                available.insert(front, index);
+                pv_first |= i == 0 && ! pv.is_empty();
+                front += 1;
+            }
+        }
+        return pv_first;
+    }
+
+    fn add_killer(&mut self, index: &T::Move, ply: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(index) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(index.clone());
+        }
+    }
+
+    // `pv` is the principal variation to try first, the one found is written to `line`
+    fn negamax(&mut self, game: &T, mut frame: Frame, pv: &[T::Move], line: &mut Vec<T::Move>) -> f32 {
+        self.nodes += 1;
+        line.clear();
+
+        let score = game.get_score();
+        if score != 0 {
+            let value = WIN_VALUE - frame.ply as f32;
+            return if score == game.current_player() {value} else {-value};
+        }
+
+        let mut available = game.get_available();
+        if available.is_empty() {
+            return 0.;
+        }
+        if frame.depth == 0 {
+            self.reached_horizon = true;
+            return self.evaluate(game);
+        }
+
+        let pv_first = self.order_moves(&mut available, pv, frame.ply);
+
+        let mut best = -f32::INFINITY;
+        let mut child_line = Vec::new();
+        for (i, index) in available.into_iter().enumerate() {
+            let mut child = game.clone();
+            child.update(index.clone());
+
+            let child_pv = if i == 0 && pv_first {&pv[1..]} else {&[]};
+            // the player may keep the turn, then the value is not negated
+            let same_player = child.current_player() == game.current_player();
+            let value = self.negamax(&child, frame.child(same_player), child_pv, &mut child_line);
+            let value = if same_player {value} else {-value};
+
+            if value > best {
+                best = value;
+                line.clear();
+                line.push(index.clone());
+                line.append(&mut child_line);
+            }
+            frame.alpha = frame.alpha.max(value);
+            if frame.alpha >= frame.beta {
+                self.add_killer(&index, frame.ply);
+                break;
+            }
+        }
+
+        return best;
+    }
+}
+
+
+#[cfg(test)]
+use std::collections::HashMap;
+#[cfg(test)]
+use super::mcts::{Node, SearchNode};
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+#[cfg(test)]
+use super::connect4::{Connect4, Connect4Heuristic};
+
+// plain minimax of the game theoretic result for the player to move
+#[cfg(test)]
+fn solve(game: &TicTacToe, cache: &mut HashMap<[[i8;3];3], i8>) -> i8 {
+    if let Some(&value) = cache.get(&game.board) {
+        return value;
+    }
+    let value = if game.get_score() != 0 {
+        game.get_score() * game.current_player()
+    }
+    else {
+        game.get_available().into_iter().map(|index| {
+            let mut child = game.clone();
+            child.update(index);
+            -solve(&child, cache)
+        }).max().unwrap_or(0)
+    };
+    cache.insert(game.board, value);
+    return value;
+}
+
+#[test]
+fn test_negamax_solves_tictactoe(){
+    let mut negamax = Negamax::new(None);
+    let result = negamax.search(&TicTacToe::from_string("...\n...\n...").unwrap(), 9);
+    assert_eq!(result.value, 0.);
+    assert_eq!(result.depth, 9);
+    assert_eq!(result.moves.len(), 9);
+
+    // every reachable position against plain minimax
+    let mut cache = HashMap::new();
+    solve(&TicTacToe::from_string("...\n...\n...").unwrap(), &mut cache);
+    for (board, value) in cache {
+        let tictactoe = TicTacToe::from_board(board);
+        let result = negamax.search(&tictactoe, 9);
+        assert_eq!(result.value.signum() as i8 * (result.value != 0.) as i8, value, "{}", tictactoe);
+
+        // the principal variation reaches the result it promises
+        let mut end = tictactoe.clone();
+        for index in result.moves.iter() {
+            end.update(*index);
+        }
+        assert_eq!(end.get_score() * tictactoe.current_player(), value, "{}", tictactoe);
+        if value != 0 {
+            assert_eq!(result.value.abs(), WIN_VALUE - result.moves.len() as f32);
+        }
+    }
+}
+
+#[test]
+fn test_negamax_prefers_fast_wins(){
+    // X can win now at (0,2) or later
+    let tictactoe = TicTacToe::from_string("XX.\nOO.\nX.O").unwrap();
+    let mut negamax = Negamax::new(None);
+    let result = negamax.search(&tictactoe, 9);
+    assert_eq!(result.best_move(), Some(&(0, 2)));
+    assert_eq!(result.value, WIN_VALUE - 1.);
+    assert_eq!(result.depth, 1);
+    assert!(result.is_decided());
+
+    let result = negamax.search(&TicTacToe::from_string("XXX\nOO.\n...").unwrap(), 9);
+    assert_eq!(result.value, -WIN_VALUE);
+    assert_eq!(result.best_move(), None);
+}
+
+#[test]
+fn test_negamax_with_evaluator(){
+    let heuristic = Connect4Heuristic::default();
+    let mut negamax = Negamax::new(Some(&heuristic));
+
+    // X has three in the bottom row with both ends open, O to move cannot block both
+    let connect4 = Connect4::from_string("......\n......\n......\n......\n.OO...\n.XXX..\n").unwrap();
+    let result = negamax.search(&connect4, 4);
+    assert!(result.is_decided());
+    assert_eq!(result.value, -(WIN_VALUE - 2.));
+    assert_eq!(result.depth, 2);
+
+    // not decided within the horizon, the value comes from the evaluator
+    let result = negamax.search(&Connect4::empty(), 3);
+    assert_eq!(result.depth, 3);
+    assert!(result.value.abs() <= 1.);
+    assert!(result.nodes > 0);
+
+    // alpha-beta visits fewer nodes than the full tree of 6 + 36 + 216 + 1 positions
+    assert!(result.nodes < 259 + 43 + 7);
+}
+
+// the move MCTS prefers is as good as the best one by negamax
+#[cfg(test)]
+fn check_agrees_with_mcts<T:GeneralGame + std::fmt::Display>(game: &T, depth: usize, iterations: usize) {
+    let mut negamax = Negamax::new(None);
+    let best = negamax.search(game, depth).value.signum();
+
+    let mut node = Node::new(game.clone(), None);
+    node.predict(iterations, 1);
+    let index = node.get_most_visited_child().unwrap().move_index.clone().unwrap();
+
+    let mut child = game.clone();
+    child.update(index.clone());
+    let value = negamax.search(&child, depth - 1).value.signum();
+    let value = if child.current_player() == game.current_player() {value} else {-value};
+    assert_eq!(value, best, "{}\n{:?}", game, index);
+}
+
+#[test]
+fn test_negamax_agrees_with_mcts(){
+    let boards = [".XX\nO..\nOX.", "..X\nO..\nXXO", "X..\n.O.\n..X", "XX.\nOO.\n...", "...\n.X.\n..."];
+    for board in boards {
+        check_agrees_with_mcts(&TicTacToe::from_string(board).unwrap(), 9, 20000);
+    }
+
+    // X wins at once, O has to block, and O loses whatever it does
+    let boards = [
+        "......\n......\n......\n......\nOOO...\nXXX...\n",
+        "......\n......\n......\n......\n......\nXXX.OO\n",
+        "......\n......\n......\n......\n.OO...\n.XXX..\n"
+    ];
+    for board in boards {
+        check_agrees_with_mcts(&Connect4::from_string(board).unwrap(), 5, 20000);
+    }
+}
+
+#[test]
+fn test_negamax_killer_moves(){
+    // the killers change the order of the moves, but not the result
+    let heuristic = Connect4Heuristic::default();
+    let connect4 = Connect4::from_string("......\n......\n......\n..O...\n..XO..\n.XXO..\n").unwrap();
+
+    let mut with_killers = Negamax::new(Some(&heuristic));
+    let mut without_killers = Negamax::new(Some(&heuristic));
+    without_killers.use_killers = false;
+
+    let result = with_killers.search(&connect4, 6);
+    let reference = without_killers.search(&connect4, 6);
+    assert_eq!(result.value, reference.value);
+    assert!(result.nodes < reference.nodes, "{} {}", result.nodes, reference.nodes);
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+
+use super::mcts::GeneralGame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OthelloMove {
+    // (row, column) of the new disc
+    Place(usize, usize),
+    // only allowed, and then mandatory, when the player to move has no other move
+    Pass
+}
+
+// Othello on an N x N board, N even. X (player 1) moves first.
+// A disc has to enclose a line of opponent discs, which are flipped. A player without such a
+// move passes, the game ends when neither player can move and the player with more discs wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Othello<const N: usize = 8> {
+    pub board: [[i8; N]; N],
+    player: i8,
+    // the moves of `player`, kept up to date by `update`, empty once the game is over
+    available: Vec<OthelloMove>
+}
+
+const DIRECTIONS : [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+impl<const N: usize> Othello<N> {
+    // the four discs in the centre
+    pub fn new() -> Othello<N> {
+        assert!(N >= 4 && N % 2 == 0, "Othello needs an even board of at least 4x4");
+        let mut board = [[0; N]; N];
+        let middle = N/2;
+        board[middle-1][middle-1] = -1;
+        board[middle-1][middle] = 1;
+        board[middle][middle-1] = 1;
+        board[middle][middle] = -1;
+        return Othello::from_board(board, 1);
+    }
+
+    pub fn from_board(board: [[i8; N]; N], player: i8) -> Othello<N> {
+        let mut othello = Othello { board, player, available: Vec::new() };
+        othello.available = othello.find_available();
+        return othello;
+    }
+
+    // the number of opponent discs in direction (drow, dcol) that a disc of `player` on (row, col) would flip
+    fn flips(&self, row: usize, col: usize, (drow, dcol): (isize, isize), player: i8) -> usize {
+        let (mut r, mut c) = (row as isize + drow, col as isize + dcol);
+        let mut count = 0;
+        while r >= 0 && r < N as isize && c >= 0 && c < N as isize {
+            match self.board[r as usize][c as usize] {
+                cell if cell == -player => count += 1,
+                cell if cell == player => return count,
+                _ => return 0
+            }
+            r += drow;
+            c += dcol;
+        }
+        return 0;
+    }
+
+    fn is_legal(&self, row: usize, col: usize, player: i8) -> bool {
+        return self.board[row][col] == 0 && DIRECTIONS.iter().any(|&direction| self.flips(row, col, direction, player) > 0);
+    }
+
+    fn placements(&self, player: i8) -> Vec<OthelloMove> {
+        let mut res = Vec::new();
+        for row in 0..N {
+            for col in 0..N {
+                if self.is_legal(row, col, player) {
+                    res.push(OthelloMove::Place(row, col));
+                }
+            }
+        }
+        return res;
+    }
+
+    fn find_available(&self) -> Vec<OthelloMove> {
+        let placements = self.placements(self.player);
+        if !placements.is_empty() {
+            return placements;
+        }
+        if !self.placements(-self.player).is_empty() {
+            return vec![OthelloMove::Pass];
+        }
+        return Vec::new();
+    }
+
+    // discs of player 1 minus discs of player -1
+    pub fn disc_difference(&self) -> i32 {
+        return self.board.iter().flatten().map(|&cell| cell as i32).sum();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        return self.available.is_empty();
+    }
+
+    // N lines of X, O and '.', optionally followed by "X to move" or "O to move", X moves by default.
+    // Spaces are ignored, so the output of `Display` can be read back.
+    pub fn from_string(val: &str) -> Option<Othello<N>> {
+        let lines = Vec::from_iter(val.lines().map(|line| line.replace(' ', "")).filter(|line| !line.is_empty()));
+        if lines.len() != N && lines.len() != N+1 {
+            return None;
+        }
+
+        let mut board = [[0; N]; N];
+        for (row, line) in lines[..N].iter().enumerate() {
+            if line.chars().count() != N {
+                return None;
+            }
+            for (col, s) in line.chars().enumerate() {
+                board[row][col] = match s {
+                    'X' => 1,
+                    'O' => -1,
+                    '.' => 0,
+                    _ => return None
+                };
+            }
+        }
+
+        let player = match lines.get(N).map(|line| line.as_str()) {
+            None | Some("Xtomove") => 1,
+            Some("Otomove") => -1,
+            _ => return None
+        };
+        return Some(Othello::from_board(board, player));
+    }
+}
+
+impl<const N: usize> GeneralGame for Othello<N> {
+    type Move = OthelloMove;
+
+    fn update(&mut self, index: OthelloMove) {
+        if let OthelloMove::Place(row, col) = index {
+            for direction in DIRECTIONS {
+                let count = self.flips(row, col, direction, self.player);
+                for k in 1..=count as isize {
+                    self.board[(row as isize + k*direction.0) as usize][(col as isize + k*direction.1) as usize] = self.player;
+                }
+            }
+            self.board[row][col] = self.player;
+        }
+        self.player = -self.player;
+        self.available = self.find_available();
+    }
+
+    fn get_score(&self) -> i8 {
+        if ! self.is_finished() {
+            return 0;
+        }
+        return self.disc_difference().signum() as i8;
+    }
+
+    fn get_available(&self) -> Vec<OthelloMove> {
+        return self.available.clone();
+    }
+
+    fn current_player(&self) -> i8 {
+        return self.player;
+    }
+}
+
+impl<const N: usize> fmt::Display for Othello<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..N {
+            for j in 0..N {
+                write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
+            }
+            writeln!(f).unwrap();
+        }
+        writeln!(f, "{} to move", if self.player == 1 {'X'} else {'O'})
+    }
+}
+
+// the number of move sequences of length `depth`, passes count as moves
+#[cfg(test)]
+fn perft<const N: usize>(othello: &Othello<N>, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut count = 0;
+    for index in othello.get_available() {
+        let mut child = othello.clone();
+        child.update(index);
+        count += perft(&child, depth - 1);
+    }
+    return count;
+}
+
+#[test]
+fn test_othello_perft(){
+    let othello = Othello::<8>::new();
+    assert_eq!(Vec::from_iter((1..=6).map(|depth| perft(&othello, depth))), [4, 12, 56, 244, 1396, 8200]);
+
+    let othello = Othello::<6>::new();
+    assert_eq!(Vec::from_iter((1..=6).map(|depth| perft(&othello, depth))), [4, 12, 56, 244, 1364, 7604]);
+}
+
+#[test]
+fn test_othello_flips(){
+    let mut othello = Othello::<8>::new();
+    assert_eq!(othello.get_available(), [OthelloMove::Place(2, 3), OthelloMove::Place(3, 2), OthelloMove::Place(4, 5), OthelloMove::Place(5, 4)]);
+    othello.update(OthelloMove::Place(2, 3));
+    assert_eq!(othello, Othello::from_string("\
+        ........\n\
+        ........\n\
+        ...X....\n\
+        ...XX...\n\
+        ...XO...\n\
+        ........\n\
+        ........\n\
+        ........\n\
+        O to move\n").unwrap());
+    assert_eq!(othello.disc_difference(), 3);
+
+    // one disc flips lines in all directions, but not past an empty cell
+    let mut othello = Othello::<6>::from_string("\
+        X.X.X.\n\
+        .OOO..\n\
+        XO.OOX\n\
+        .OOO..\n\
+        X.O...\n\
+        ..X..X\n").unwrap();
+    assert!(othello.get_available().contains(&OthelloMove::Place(2, 2)));
+    othello.update(OthelloMove::Place(2, 2));
+    assert_eq!(othello.to_string(), "\
+X . X . X . \n\
+. X X X . . \n\
+X X X X X X \n\
+. X X O . . \n\
+X . X . . . \n\
+. . X . . X \n\
+O to move\n");
+    assert_eq!(Othello::from_string(&othello.to_string()), Some(othello));
+}
+
+#[test]
+fn test_othello_pass(){
+    // X cannot flank the corner, O can
+    let mut othello = Othello::<4>::from_string("\
+        OXXX\n\
+        XXXX\n\
+        XXXX\n\
+        ....\n\
+        X to move\n").unwrap();
+    assert_eq!(othello.get_available(), [OthelloMove::Pass]);
+    assert_eq!(othello.get_score(), 0);
+    assert!(othello.try_update(OthelloMove::Place(3, 0)).is_err());
+
+    othello.update(OthelloMove::Pass);
+    assert_eq!(othello.current_player(), -1);
+    assert_eq!(othello.get_available(), [OthelloMove::Place(3, 0), OthelloMove::Place(3, 3)]);
+    othello.update(OthelloMove::Place(3, 0));
+    assert_eq!(othello, Othello::from_string("OXXX\nOXXX\nOXXX\nO...\nX to move\n").unwrap());
+
+    // passing again
+    assert_eq!(othello.get_available(), [OthelloMove::Pass]);
+    othello.update(OthelloMove::Pass);
+    assert_eq!(othello.get_available(), [OthelloMove::Place(3, 2), OthelloMove::Place(3, 3)]);
+    assert!(! othello.is_finished());
+}
+
+#[test]
+fn test_othello_score(){
+    let othello = Othello::<4>::from_string("OOOO\nOOOO\nXXXX\nXXXX\n").unwrap();
+    assert!(othello.is_finished());
+    assert_eq!(othello.get_score(), 0);
+
+    let othello = Othello::<4>::from_string("OOOO\nOOOO\nOXXX\nXXXX\nO to move").unwrap();
+    assert_eq!(othello.disc_difference(), -2);
+    assert_eq!(othello.get_score(), -1);
+
+    // the board is not full, but nobody can move
+    let othello = Othello::<4>::from_string("X...\n....\n....\n....\n").unwrap();
+    assert!(othello.is_finished());
+    assert_eq!(othello.get_available(), []);
+    assert_eq!(othello.get_score(), 1);
+
+    assert_eq!(Othello::<4>::from_string("OOOO\nOOOO\nXXXX\n"), None);
+    assert_eq!(Othello::<4>::from_string("OOOO\nOOOO\nXXXX\nXXX\n"), None);
+    assert_eq!(Othello::<4>::from_string("OOOO\nOOOO\nXXXX\nXXXX\nY to move\n"), None);
+}
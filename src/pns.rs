@@ -0,0 +1,323 @@
+use super::mcts::GeneralGame;
+
+// Proof and disproof numbers above this are treated as infinite
+const INFINITE : u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnsResult<M> {
+    // game theoretic result for the player to move, None if the node budget ran out
+    pub outcome: Option<Outcome>,
+    // a move achieving a win or a draw, None for losses and unsolved positions
+    pub best_move: Option<M>,
+    pub nodes: usize
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PnsVariant {
+    Pn,
+    // every new leaf is initialised by a proof-number search with the given node budget
+    Pn2(usize)
+}
+
+// What the player to move at the root is trying to prove.
+// Draws are disproofs of a win but proofs of not losing, so they are settled by a second search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Win,
+    NotLose
+}
+
+struct PnNode<T:GeneralGame> {
+    game: T,
+    proof: u64,
+    disproof: u64,
+    // the attacker is to move, so one proven child is enough
+    is_or: bool,
+    expanded: bool,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    move_index: Option<T::Move>
+}
+
+// Proof-number search (Allis 1994). The tree is kept in an arena, `max_nodes` bounds its size.
+pub struct ProofNumberSearch {
+    pub max_nodes: usize,
+    pub variant: PnsVariant
+}
+
+impl ProofNumberSearch {
+    pub fn new(max_nodes: usize, variant: PnsVariant) -> ProofNumberSearch {
+        return ProofNumberSearch { max_nodes, variant };
+    }
+
+    pub fn solve<T:GeneralGame>(&self, game: &T) -> PnsResult<T::Move> {
+        let attacker = game.current_player();
+
+        let (win, win_move, win_nodes) = self.prove(game, attacker, Target::Win);
+        match win {
+            Some(true) => return PnsResult { outcome: Some(Outcome::Win), best_move: win_move, nodes: win_nodes },
+            None => return PnsResult { outcome: None, best_move: None, nodes: win_nodes },
+            Some(false) => ()
+        }
+
+        let (not_lose, draw_move, draw_nodes) = self.prove(game, attacker, Target::NotLose);
+        let nodes = win_nodes + draw_nodes;
+        return match not_lose {
+            Some(true) => PnsResult { outcome: Some(Outcome::Draw), best_move: draw_move, nodes },
+            Some(false) => PnsResult { outcome: Some(Outcome::Loss), best_move: None, nodes },
+            None => PnsResult { outcome: None, best_move: None, nodes }
+        };
+    }
+
+    // Runs one search, returns whether the target was proven, the proving move and the number of nodes created
+    fn prove<T:GeneralGame>(&self, game: &T, attacker: i8, target: Target) -> (Option<bool>, Option<T::Move>, usize) {
+        let (tree, nodes) = self.search(game, attacker, target);
+
+        let root = &tree[0];
+        if root.proof == 0 {
+            let proving = root.children.iter().find(|&&child| tree[child].proof == 0);
+            return (Some(true), proving.and_then(|&child| tree[child].move_index.clone()), nodes);
+        }
+        if root.disproof == 0 {
+            return (Some(false), None, nodes);
+        }
+        return (None, None, nodes);
+    }
+
+    // Grows the tree until the root is proven, disproven or the tree is full
+    fn search<T:GeneralGame>(&self, game: &T, attacker: i8, target: Target) -> (Vec<PnNode<T>>, usize) {
+        let mut tree = vec![PnNode { game: game.clone(), proof: 1, disproof: 1, is_or: true, expanded: false, parent: None, children: Vec::new(), move_index: None }];
+        let mut nodes = 1usize;
+        Self::set_numbers(&mut tree[0], attacker, target);
+
+        while tree[0].proof != 0 && tree[0].disproof != 0 && tree.len() < self.max_nodes {
+            let leaf = Self::most_proving(&tree);
+            nodes += self.expand(&mut tree, leaf, attacker, target);
+            Self::update_ancestors(&mut tree, leaf);
+        }
+
+        return (tree, nodes);
+    }
+
+    // numbers of a node that is not expanded yet
+    fn set_numbers<T:GeneralGame>(node: &mut PnNode<T>, attacker: i8, target: Target) {
+        node.is_or = node.game.current_player() == attacker;
+
+        let score = node.game.get_score();
+        let proven = if score != 0 {
+            Some(score == attacker)
+        }
+        else if node.game.get_available().is_empty() {
+            Some(target == Target::NotLose)
+        }
+        else {
+            None
+        };
+
+        (node.proof, node.disproof) = match proven {
+            Some(true) => (0, INFINITE),
+            Some(false) => (INFINITE, 0),
+            None => (1, 1)
+        };
+    }
+
+    fn most_proving<T:GeneralGame>(tree: &[PnNode<T>]) -> usize {
+        let mut current = 0usize;
+        while tree[current].expanded {
+            let node = &tree[current];
+            current = if node.is_or {
+                *node.children.iter().find(|&&child| tree[child].proof == node.proof).unwrap()
+            }
+            else {
+                *node.children.iter().find(|&&child| tree[child].disproof == node.disproof).unwrap()
+            };
+        }
+        return current;
+    }
+
+    fn expand<T:GeneralGame>(&self, tree: &mut Vec<PnNode<T>>, leaf: usize, attacker: i8, target: Target) -> usize {
+        let mut nodes = 0usize;
+
+        for index in tree[leaf].game.get_available() {
+            let mut game = tree[leaf].game.clone();
+            game.update(index.clone());
+
+            let mut child = PnNode { game, proof: 1, disproof: 1, is_or: true, expanded: false, parent: Some(leaf), children: Vec::new(), move_index: Some(index) };
+            Self::set_numbers(&mut child, attacker, target);
+
+            // PN²: a small search below the child gives better initial numbers, its tree is thrown away
+            if let PnsVariant::Pn2(inner_nodes) = self.variant {
+                if child.proof != 0 && child.disproof != 0 {
+                    let inner = ProofNumberSearch { max_nodes: inner_nodes, variant: PnsVariant::Pn };
+                    let (inner_tree, inner_count) = inner.search(&child.game, attacker, target);
+                    (child.proof, child.disproof) = (inner_tree[0].proof, inner_tree[0].disproof);
+                    nodes += inner_count;
+                }
+            }
+
+            let id = tree.len();
+            tree.push(child);
+            tree[leaf].children.push(id);
+            nodes += 1;
+        }
+
+        tree[leaf].expanded = true;
+        return nodes;
+    }
+
+    fn update_ancestors<T:GeneralGame>(tree: &mut [PnNode<T>], node: usize) {
+        let mut current = Some(node);
+        while let Some(id) = current {
+            let children = &tree[id].children;
+            let (proof, disproof) = if children.is_empty() {
+                (tree[id].proof, tree[id].disproof)
+            }
+            else if tree[id].is_or {
+                (children.iter().map(|&child| tree[child].proof).min().unwrap(),
+                 children.iter().fold(0u64, |sum, &child| sum.saturating_add(tree[child].disproof)))
+            }
+            else {
+                (children.iter().fold(0u64, |sum, &child| sum.saturating_add(tree[child].proof)),
+                 children.iter().map(|&child| tree[child].disproof).min().unwrap())
+            };
+
+            tree[id].proof = proof;
+            tree[id].disproof = disproof;
+            current = tree[id].parent;
+        }
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+#[cfg(test)]
+use super::connect4::Connect4;
+#[cfg(test)]
+use super::negamax::Negamax;
+#[cfg(test)]
+use std::collections::HashMap;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+// game theoretic result according to a full negamax search
+#[cfg(test)]
+fn negamax_outcome<T:GeneralGame>(game: &T) -> Outcome {
+    let value = Negamax::new(None).search(game, 64).value;
+    return if value > 0. {Outcome::Win} else if value < 0. {Outcome::Loss} else {Outcome::Draw};
+}
+
+// checks the outcome and that the proving move keeps it
+#[cfg(test)]
+fn check_result<T:GeneralGame, F:Fn(&T) -> Outcome>(game: &T, result: &PnsResult<T::Move>, outcome_of: &F) {
+    let outcome = outcome_of(game);
+    assert_eq!(result.outcome, Some(outcome));
+
+    match &result.best_move {
+        Some(index) => {
+            let mut child = game.clone();
+            child.update(index.clone());
+            let child_outcome = if child.get_score() != 0 {Outcome::Loss} else {outcome_of(&child)};
+            match outcome {
+                Outcome::Win => assert_eq!(child_outcome, Outcome::Loss),
+                Outcome::Draw => assert_eq!(child_outcome, Outcome::Draw),
+                Outcome::Loss => panic!("A lost position has no proving move")
+            }
+        }
+        None => assert_eq!(outcome, Outcome::Loss)
+    }
+}
+
+#[test]
+fn test_pns_tictactoe(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    for variant in [PnsVariant::Pn, PnsVariant::Pn2(50)] {
+        let result = ProofNumberSearch::new(100000, variant).solve(&empty);
+        assert_eq!(result.outcome, Some(Outcome::Draw));
+        check_result(&empty, &result, &negamax_outcome);
+    }
+
+    // O to move, only (0,0) wins
+    let tictactoe = TicTacToe::from_string(".XX\nO..\nOX.").unwrap();
+    let result = ProofNumberSearch::new(100000, PnsVariant::Pn).solve(&tictactoe);
+    assert_eq!(result.outcome, Some(Outcome::Win));
+    assert_eq!(result.best_move, Some((0, 0)));
+
+    let mut positions = vec![empty];
+    let mut next = 0usize;
+    while next < positions.len() {
+        let current = positions[next].clone();
+        next += 1;
+        if current.get_score() != 0 {
+            continue;
+        }
+        for index in current.get_available() {
+            let mut child = current.clone();
+            child.update(index);
+            if ! positions.contains(&child) {
+                positions.push(child);
+            }
+        }
+    }
+    let mut outcomes = HashMap::new();
+    for tictactoe in positions.iter().rev() {
+        outcomes.insert(tictactoe.board, negamax_outcome(tictactoe));
+    }
+    let outcome_of = |tictactoe: &TicTacToe| outcomes[&tictactoe.board];
+
+    // every unfinished position
+    for tictactoe in positions.iter().filter(|tictactoe| tictactoe.get_score() == 0 && !tictactoe.get_available().is_empty()) {
+        check_result(tictactoe, &ProofNumberSearch::new(100000, PnsVariant::Pn).solve(tictactoe), &outcome_of);
+        check_result(tictactoe, &ProofNumberSearch::new(100000, PnsVariant::Pn2(20)).solve(tictactoe), &outcome_of);
+    }
+}
+
+#[test]
+fn test_pns_connect4(){
+    // O completes the bottom row
+    let connect4 = Connect4::from_string("X.....\nO.....\nX..O..\nO..X..\nXO.XX.\nXX.OOO\n").unwrap();
+    let result = ProofNumberSearch::new(100000, PnsVariant::Pn).solve(&connect4);
+    assert_eq!(result.outcome, Some(Outcome::Win));
+    assert_eq!(result.best_move, Some(2));
+
+    // X has three in the bottom row with both ends open, O to move cannot block both
+    let connect4 = Connect4::from_string("......\n......\n......\n......\n.OO...\n.XXX..\n").unwrap();
+    for variant in [PnsVariant::Pn, PnsVariant::Pn2(100)] {
+        let result = ProofNumberSearch::new(100000, variant).solve(&connect4);
+        assert_eq!(result.outcome, Some(Outcome::Loss));
+        assert_eq!(result.best_move, None);
+    }
+
+    // positions late in random games against negamax
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut solved = 0;
+    while solved < 20 {
+        let mut connect4 = Connect4::empty();
+        for _ in 0..24 {
+            if connect4.get_score() == 0 && !connect4.get_available().is_empty() {
+                connect4.update(*connect4.get_available().choose(&mut rng).unwrap());
+            }
+        }
+        if connect4.get_score() != 0 || connect4.get_available().is_empty() {
+            continue;
+        }
+        check_result(&connect4, &ProofNumberSearch::new(1000000, PnsVariant::Pn).solve(&connect4), &negamax_outcome);
+        check_result(&connect4, &ProofNumberSearch::new(1000000, PnsVariant::Pn2(100)).solve(&connect4), &negamax_outcome);
+        solved += 1;
+    }
+}
+
+#[test]
+fn test_pns_budget(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let result = ProofNumberSearch::new(20, PnsVariant::Pn).solve(&empty);
+    assert_eq!(result, PnsResult { outcome: None, best_move: None, nodes: result.nodes });
+    assert!(result.nodes < 30);
+}
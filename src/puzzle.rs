@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+// Single-player counterpart of `GeneralGame`: there is no opponent and the goal is to maximise the final score.
+// A puzzle is over when there are no moves available.
+pub trait Puzzle : Clone {
+    fn update(&mut self, index:usize);
+    fn get_score(&self) -> f32;
+    fn get_available(&self) -> Vec<usize>;
+
+    // identifies a move across positions, used by NRPA to share the policy weights between positions
+    fn move_code(&self, index:usize) -> u64 {
+        return index as u64;
+    }
+}
+
+pub fn random_playout<T:Puzzle, R:Rng>(puzzle: &T, rng: &mut R) -> (f32, Vec<usize>) {
+    let mut current = puzzle.clone();
+    let mut sequence = Vec::new();
+
+    loop {
+        let available = current.get_available();
+        if available.is_empty() {
+            return (current.get_score(), sequence);
+        }
+
+        let index = *available.choose(rng).unwrap();
+        current.update(index);
+        sequence.push(index);
+    }
+}
+
+// Nested Monte Carlo Search (Cazenave 2009), level 0 is a random playout.
+// Returns the best score and the move sequence reaching it.
+pub fn nested_monte_carlo<T:Puzzle, R:Rng>(puzzle: &T, level: usize, rng: &mut R) -> (f32, Vec<usize>) {
+    if level == 0 {
+        return random_playout(puzzle, rng);
+    }
+
+    let mut current = puzzle.clone();
+    let mut played : Vec<usize> = Vec::new();
+    let mut best_score = f32::NEG_INFINITY;
+    let mut best_sequence : Vec<usize> = Vec::new();
+
+    loop {
+        let available = current.get_available();
+        if available.is_empty() {
+            break;
+        }
+
+        for index in available {
+            let mut child = current.clone();
+            child.update(index);
+            let (score, sequence) = nested_monte_carlo(&child, level-1, rng);
+
+            if score > best_score {
+                best_score = score;
+                best_sequence = played.clone();
+                best_sequence.push(index);
+                best_sequence.extend(sequence);
+            }
+        }
+
+        // follow the best sequence found so far
+        let index = best_sequence[played.len()];
+        current.update(index);
+        played.push(index);
+    }
+
+    if best_score == f32::NEG_INFINITY {
+        return (current.get_score(), played);
+    }
+
+    return (best_score, best_sequence);
+}
+
+// Nested Rollout Policy Adaptation (Rosin 2011).
+// Every level runs `iterations` searches of the level below and adapts the playout policy towards the best sequence.
+pub fn nrpa<T:Puzzle, R:Rng>(puzzle: &T, level: usize, iterations: usize, alpha: f32, rng: &mut R) -> (f32, Vec<usize>) {
+    let mut policy : HashMap<u64, f32> = HashMap::new();
+    return nrpa_level(puzzle, level, iterations, alpha, &mut policy, rng);
+}
+
+fn nrpa_level<T:Puzzle, R:Rng>(puzzle: &T, level: usize, iterations: usize, alpha: f32, policy: &mut HashMap<u64, f32>, rng: &mut R) -> (f32, Vec<usize>) {
+    if level == 0 {
+        return policy_playout(puzzle, policy, rng);
+    }
+
+    let mut best_score = f32::NEG_INFINITY;
+    let mut best_sequence : Vec<usize> = Vec::new();
+
+    for _ in 0..iterations {
+        let mut child_policy = policy.clone();
+        let (score, sequence) = nrpa_level(puzzle, level-1, iterations, alpha, &mut child_policy, rng);
+
+        if score >= best_score {
+            best_score = score;
+            best_sequence = sequence;
+        }
+
+        adapt_policy(puzzle, &best_sequence, alpha, policy);
+    }
+
+    return (best_score, best_sequence);
+}
+
+fn policy_playout<T:Puzzle, R:Rng>(puzzle: &T, policy: &HashMap<u64, f32>, rng: &mut R) -> (f32, Vec<usize>) {
+    let mut current = puzzle.clone();
+    let mut sequence = Vec::new();
+
+    loop {
+        let available = current.get_available();
+        if available.is_empty() {
+            return (current.get_score(), sequence);
+        }
+
+        let weights = Vec::from_iter(available.iter().map(|&index| policy.get(&current.move_code(index)).unwrap_or(&0.).exp()));
+        let mut target = rng.gen::<f32>() * weights.iter().sum::<f32>();
+        let mut choice = available.len() - 1;
+        for (i, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                choice = i;
+                break;
+            }
+            target -= weight;
+        }
+
+        current.update(available[choice]);
+        sequence.push(available[choice]);
+    }
+}
+
+fn adapt_policy<T:Puzzle>(puzzle: &T, sequence: &[usize], alpha: f32, policy: &mut HashMap<u64, f32>) {
+    let old_policy = policy.clone();
+    let mut current = puzzle.clone();
+
+    for &index in sequence.iter() {
+        let available = current.get_available();
+        let codes = Vec::from_iter(available.iter().map(|&i| current.move_code(i)));
+        let weights = Vec::from_iter(codes.iter().map(|code| old_policy.get(code).unwrap_or(&0.).exp()));
+        let sum = weights.iter().sum::<f32>();
+
+        *policy.entry(current.move_code(index)).or_insert(0.) += alpha;
+        for (code, weight) in codes.iter().zip(weights.iter()) {
+            *policy.entry(*code).or_insert(0.) -= alpha * weight / sum;
+        }
+
+        current.update(index);
+    }
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::samegame::SameGame;
+
+#[cfg(test)]
+fn replay(puzzle: &SameGame, sequence: &[usize]) -> SameGame {
+    let mut current = puzzle.clone();
+    for &index in sequence.iter() {
+        assert!(current.get_available().contains(&index));
+        current.update(index);
+    }
+    return current;
+}
+
+#[test]
+fn test_puzzle_random_playout(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let samegame = SameGame::from_string("1122\n2211\n1212").unwrap();
+    let (score, sequence) = random_playout(&samegame, &mut rng);
+
+    let end = replay(&samegame, &sequence);
+    assert_eq!(end.get_score(), score);
+    assert_eq!(end.get_available(), []);
+}
+
+#[test]
+fn test_puzzle_nested_monte_carlo(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    // removing the 2s first is the only way to clear the board
+    let samegame = SameGame::from_string("11221").unwrap();
+    for _ in 0..5 {
+        let (score, sequence) = nested_monte_carlo(&samegame, 1, &mut rng);
+        assert_eq!(score, 1001.);
+        assert_eq!(sequence, [2, 0]);
+    }
+
+    let samegame = SameGame::random(5, 5, 3, &mut rng);
+    let (score, sequence) = nested_monte_carlo(&samegame, 2, &mut rng);
+    let end = replay(&samegame, &sequence);
+    assert_eq!(end.get_score(), score);
+    assert_eq!(end.get_available(), []);
+}
+
+#[test]
+fn test_puzzle_nrpa(){
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let samegame = SameGame::from_string("11221").unwrap();
+    for _ in 0..5 {
+        let (score, sequence) = nrpa(&samegame, 2, 10, 1., &mut rng);
+        assert_eq!(score, 1001.);
+        assert_eq!(sequence, [2, 0]);
+    }
+
+    let samegame = SameGame::random(5, 5, 3, &mut rng);
+    let (score, sequence) = nrpa(&samegame, 2, 10, 1., &mut rng);
+    let end = replay(&samegame, &sequence);
+    assert_eq!(end.get_score(), score);
+    assert_eq!(end.get_available(), []);
+}
+
+#[test]
+fn test_puzzle_seeded(){
+    // the same seed gives the same board and the same solutions
+    let run = |seed: u64| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let samegame = SameGame::random(5, 5, 3, &mut rng);
+        return (samegame.to_string(), nested_monte_carlo(&samegame, 1, &mut rng), nrpa(&samegame, 1, 10, 1., &mut rng));
+    };
+    assert_eq!(run(1), run(1));
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+
+use rand::Rng;
+
+use super::puzzle::Puzzle;
+
+// SameGame: removing a group of n >= 2 connected tiles of the same colour scores (n-2)^2,
+// tiles fall down into the gaps and empty columns are closed from the right.
+// Clearing the whole board is rewarded with a bonus of 1000.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SameGame {
+    pub width: usize,
+    pub height: usize,
+    // row major, row 0 is the top, 0 is an empty cell
+    pub board: Vec<u8>,
+    pub score: f32
+}
+
+impl SameGame {
+    pub fn from_string(val : &str) -> Option<SameGame> {
+        let rows = Vec::from_iter(val.lines().map(|line| line.trim_end_matches('\r')).filter(|line| !line.is_empty()));
+        if rows.is_empty() {
+            return None;
+        }
+
+        let width = rows[0].len();
+        let height = rows.len();
+        let mut board = Vec::with_capacity(width*height);
+        for row in rows {
+            if row.len() != width {
+                return None;
+            }
+            for s in row.chars() {
+                let colour = match s {
+                    '.' => 0u8,
+                    '1'..='9' => s.to_digit(10).unwrap() as u8,
+                    _ => return None
+                };
+                board.push(colour);
+            }
+        }
+
+        // check for floating tiles
+        for col in 0..width {
+            let mut gap = false;
+            for row in (0..height).rev() {
+                if board[row*width + col] == 0 {
+                    gap = true;
+                }
+                else if gap {
+                    return None;
+                }
+            }
+        }
+
+        return Some(SameGame { width, height, board, score: 0. });
+    }
+
+    pub fn random<R:Rng>(width: usize, height: usize, colours: u8, rng: &mut R) -> SameGame {
+        let board = Vec::from_iter((0..width*height).map(|_| rng.gen_range(1..=colours)));
+        return SameGame { width, height, board, score: 0. };
+    }
+
+    // cells of the group containing `index`, sorted
+    pub fn get_group(&self, index: usize) -> Vec<usize> {
+        let colour = self.board[index];
+        if colour == 0 {
+            return Vec::new();
+        }
+
+        let mut group = vec![index];
+        let mut visited = vec![false; self.board.len()];
+        visited[index] = true;
+
+        let mut next = 0usize;
+        while next < group.len() {
+            let cell = group[next];
+            next += 1;
+
+            let (row, col) = (cell / self.width, cell % self.width);
+            let mut neighbours = Vec::with_capacity(4);
+            if row > 0 { neighbours.push(cell - self.width); }
+            if row + 1 < self.height { neighbours.push(cell + self.width); }
+            if col > 0 { neighbours.push(cell - 1); }
+            if col + 1 < self.width { neighbours.push(cell + 1); }
+
+            for neighbour in neighbours {
+                if !visited[neighbour] && self.board[neighbour] == colour {
+                    visited[neighbour] = true;
+                    group.push(neighbour);
+                }
+            }
+        }
+
+        group.sort();
+        return group;
+    }
+
+    fn collapse(&mut self) {
+        // tiles fall down
+        for col in 0..self.width {
+            let mut target = self.height;
+            for row in (0..self.height).rev() {
+                let colour = self.board[row*self.width + col];
+                if colour != 0 {
+                    target -= 1;
+                    self.board[row*self.width + col] = 0;
+                    self.board[target*self.width + col] = colour;
+                }
+            }
+        }
+
+        // empty columns are removed
+        let mut target = 0usize;
+        for col in 0..self.width {
+            if self.board[(self.height-1)*self.width + col] == 0 {
+                continue;
+            }
+            if target != col {
+                for row in 0..self.height {
+                    self.board[row*self.width + target] = self.board[row*self.width + col];
+                    self.board[row*self.width + col] = 0;
+                }
+            }
+            target += 1;
+        }
+    }
+}
+
+impl Puzzle for SameGame {
+    fn get_score(&self) -> f32 {
+        return self.score;
+    }
+
+    // every group of two or more tiles is a move, identified by its first cell
+    fn get_available(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.board.len()];
+        let mut available = Vec::new();
+
+        for index in 0..self.board.len() {
+            if seen[index] || self.board[index] == 0 {
+                continue;
+            }
+            let group = self.get_group(index);
+            for &cell in group.iter() {
+                seen[cell] = true;
+            }
+            if group.len() >= 2 {
+                available.push(index);
+            }
+        }
+
+        return available;
+    }
+
+    fn update(&mut self, index:usize) {
+        let group = self.get_group(index);
+        if group.len() < 2 {
+            panic!("Invalid move.");
+        }
+
+        for &cell in group.iter() {
+            self.board[cell] = 0;
+        }
+        self.score += ((group.len() - 2) * (group.len() - 2)) as f32;
+
+        self.collapse();
+
+        if self.board.iter().all(|&colour| colour == 0) {
+            self.score += 1000.;
+        }
+    }
+
+    fn move_code(&self, index:usize) -> u64 {
+        return (self.board[index] as u64) * (self.board.len() as u64) + index as u64;
+    }
+}
+
+impl fmt::Display for SameGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let colour = self.board[row*self.width + col];
+                if colour == 0 {
+                    write!(f, ". ").unwrap();
+                }
+                else {
+                    write!(f, "{} ", colour).unwrap();
+                }
+            }
+            writeln!(f).unwrap();
+        }
+        write!(f, "")
+    }
+}
+
+#[test]
+fn test_samegame_from_string(){
+    let samegame = SameGame::from_string("1.3\n123\n").unwrap();
+    assert_eq!(samegame, SameGame { width: 3, height: 2, board: vec![1,0,3,1,2,3], score: 0. });
+
+    assert_eq!(SameGame::from_string("1.3\n12\n"), None);
+    assert_eq!(SameGame::from_string("123\n1.3\n"), None);
+    assert_eq!(SameGame::from_string("1a3\n123\n"), None);
+}
+
+#[test]
+fn test_samegame_available(){
+    let samegame = SameGame::from_string("1122\n2312\n3311").unwrap();
+    assert_eq!(samegame.get_available(), [0, 2, 5, 6]);
+    assert_eq!(samegame.get_group(2), [2, 3, 7]);
+
+    let samegame = SameGame::from_string("123\n312").unwrap();
+    assert_eq!(samegame.get_available(), []);
+}
+
+#[test]
+fn test_samegame_update(){
+    let mut samegame = SameGame::from_string("1122\n2312\n3311").unwrap();
+
+    samegame.update(2);
+    assert_eq!(format!("{}", samegame), "1 1 . . \n2 3 1 . \n3 3 1 1 \n");
+    assert_eq!(samegame.get_score(), 1.);
+
+    // the left column empties and the board shifts left
+    let mut samegame = SameGame::from_string("1.\n12\n12").unwrap();
+    samegame.update(0);
+    assert_eq!(format!("{}", samegame), ". . \n2 . \n2 . \n");
+    assert_eq!(samegame.get_score(), 1.);
+    samegame.update(2);
+    assert_eq!(samegame.get_score(), 1001.);
+
+    let mut samegame = SameGame::from_string("2.\n12\n12").unwrap();
+    samegame.update(2);
+    assert_eq!(format!("{}", samegame), ". . \n. 2 \n2 2 \n");
+    assert_eq!(samegame.get_available(), [3]);
+    samegame.update(3);
+    assert_eq!(samegame.get_score(), 1001.);
+}
+
+#[test]
+fn test_samegame_fmt(){
+    let samegame = SameGame { width: 3, height: 2, board: vec![1,0,3,1,2,3], score: 0. };
+    assert_eq!(format!("{}", samegame), "1 . 3 \n1 2 3 \n");
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::{SeedableRng, rngs::StdRng};
+
+use super::mcts::GeneralGame;
+use super::agent::MctsAgent;
+use super::tournament::{Entrant, TournamentConfig, Schedule, PairResult, run_tournament};
+
+// Search budget of an MCTS bot per move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub iterations: usize,
+    pub rollouts: u64
+}
+
+impl Budget {
+    pub fn new(iterations: usize, rollouts: u64) -> Budget {
+        return Budget { iterations, rollouts };
+    }
+
+    pub fn name(&self) -> String {
+        return format!("mcts {}x{}", self.iterations, self.rollouts);
+    }
+}
+
+pub struct ScalingConfig {
+    // games of every budget against the reference, the colours alternate
+    pub games_per_budget: usize,
+    pub threads: usize,
+    pub seed: u64
+}
+
+impl Default for ScalingConfig {
+    fn default() -> ScalingConfig {
+        return ScalingConfig { games_per_budget: 100, threads: 4, seed: 0 };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalingPoint {
+    pub budget: Budget,
+    // counted for the bot with `budget`
+    pub result: PairResult
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalingExperiment {
+    pub reference: Budget,
+    pub points: Vec<ScalingPoint>
+}
+
+// Plays every budget against the reference from `start`
+pub fn run_scaling<T:GeneralGame + PartialEq + Sync + 'static>(start: &T, budgets: &[Budget], reference: Budget, config: &ScalingConfig) -> ScalingExperiment {
+    let entrants = Vec::from_iter([reference].iter().chain(budgets.iter()).map(|&budget| Entrant::<T> {
+        name: budget.name(),
+        make: Box::new(move |seed| Box::new(MctsAgent::new(budget.iterations, budget.rollouts, StdRng::seed_from_u64(seed))))
+    }));
+    let tournament = TournamentConfig {
+        schedule: Schedule::Gauntlet, games_per_pair: config.games_per_budget, threads: config.threads, sprt: None, seed: config.seed
+    };
+    let result = run_tournament(start, &entrants, &tournament);
+
+    let points = Vec::from_iter(result.pairs.iter().map(|pair| ScalingPoint { budget: budgets[pair.second - 1], result: pair.swapped() }));
+    return ScalingExperiment { reference, points };
+}
+
+// infinite ratings are drawn at the border of the plot
+const PLOT_ELO_LIMIT : f32 = 800.;
+const PLOT_WIDTH : f32 = 640.;
+const PLOT_HEIGHT : f32 = 400.;
+const PLOT_MARGIN : f32 = 50.;
+const PLOT_COLOURS : [&str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd", "#ff7f0e", "#8c564b"];
+
+impl ScalingExperiment {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("iterations,rollouts,games,wins,draws,losses,win_rate,score,elo,elo_low,elo_high\n");
+        for point in self.points.iter() {
+            let result = &point.result;
+            let (elo, low, high) = result.elo();
+            csv.push_str(&format!("{},{},{},{},{},{},{:.4},{:.4},{:.1},{:.1},{:.1}\n",
+                point.budget.iterations, point.budget.rollouts, result.games(), result.wins, result.draws, result.losses,
+                result.wins as f32 / result.games() as f32, result.score(), elo, low, high));
+        }
+        return csv;
+    }
+
+    // Elo against the reference over the rollouts per move on a logarithmic axis, one line per number of rollouts per iteration
+    pub fn to_svg(&self) -> String {
+        let total = |budget: &Budget| ((budget.iterations as u64 * budget.rollouts).max(1) as f32).log10();
+        let clamp = |elo: f32| elo.clamp(-PLOT_ELO_LIMIT, PLOT_ELO_LIMIT);
+
+        let min_x = self.points.iter().map(|point| total(&point.budget)).fold(f32::INFINITY, f32::min);
+        let max_x = self.points.iter().map(|point| total(&point.budget)).fold(f32::NEG_INFINITY, f32::max);
+        let (min_x, max_x) = if min_x < max_x {(min_x, max_x)} else {(min_x - 0.5, min_x + 0.5)};
+        let elos = Vec::from_iter(self.points.iter().flat_map(|point| {
+            let (_, low, high) = point.result.elo();
+            [clamp(low), clamp(high)]
+        }));
+        let min_y = elos.iter().cloned().fold(0f32, f32::min);
+        let max_y = elos.iter().cloned().fold(0f32, f32::max);
+        let (min_y, max_y) = if min_y < max_y {(min_y, max_y)} else {(-100., 100.)};
+
+        let x = |value: f32| PLOT_MARGIN + (value - min_x) / (max_x - min_x) * (PLOT_WIDTH - 2. * PLOT_MARGIN);
+        let y = |value: f32| PLOT_HEIGHT - PLOT_MARGIN - (value - min_y) / (max_y - min_y) * (PLOT_HEIGHT - 2. * PLOT_MARGIN);
+
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"12\">\n", PLOT_WIDTH, PLOT_HEIGHT);
+        svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", PLOT_WIDTH, PLOT_HEIGHT));
+
+        // axes, the horizontal one at the strength of the reference
+        svg.push_str(&format!("<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"black\"/>\n", PLOT_MARGIN, PLOT_MARGIN, PLOT_HEIGHT - PLOT_MARGIN));
+        svg.push_str(&format!("<line x1=\"{0}\" y1=\"{1:.1}\" x2=\"{2}\" y2=\"{1:.1}\" stroke=\"gray\" stroke-dasharray=\"4\"/>\n", PLOT_MARGIN, y(0.), PLOT_WIDTH - PLOT_MARGIN));
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">rollouts per move (log scale)</text>\n", PLOT_WIDTH / 2., PLOT_HEIGHT - 10.));
+        svg.push_str(&format!("<text x=\"15\" y=\"{0}\" text-anchor=\"middle\" transform=\"rotate(-90 15 {0})\">Elo against {1}</text>\n", PLOT_HEIGHT / 2., self.reference.name()));
+        for power in (min_x.ceil() as i32)..=(max_x.floor() as i32) {
+            svg.push_str(&format!("<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n", x(power as f32), PLOT_HEIGHT - PLOT_MARGIN + 15., 10u64.pow(power as u32)));
+        }
+        svg.push_str(&format!("<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{:.0}</text>\n", PLOT_MARGIN - 5., y(max_y) + 4., max_y));
+        svg.push_str(&format!("<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{:.0}</text>\n", PLOT_MARGIN - 5., y(min_y) + 4., min_y));
+
+        let mut rollouts = Vec::from_iter(self.points.iter().map(|point| point.budget.rollouts));
+        rollouts.sort();
+        rollouts.dedup();
+        for (series, &series_rollouts) in rollouts.iter().enumerate() {
+            let colour = PLOT_COLOURS[series % PLOT_COLOURS.len()];
+            let mut points = Vec::from_iter(self.points.iter().filter(|point| point.budget.rollouts == series_rollouts));
+            points.sort_by(|a, b| total(&a.budget).partial_cmp(&total(&b.budget)).unwrap());
+
+            let line = Vec::from_iter(points.iter().map(|point| format!("{:.1},{:.1}", x(total(&point.budget)), y(clamp(point.result.elo().0)))));
+            svg.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"{}\"/>\n", line.join(" "), colour));
+            for point in points {
+                let (elo, low, high) = point.result.elo();
+                let px = x(total(&point.budget));
+                svg.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\" stroke=\"{3}\"/>\n", px, y(clamp(low)), y(clamp(high)), colour));
+                svg.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"/>\n", px, y(clamp(elo)), colour));
+            }
+            svg.push_str(&format!("<text x=\"{}\" y=\"{}\" fill=\"{}\">{} rollouts per iteration</text>\n", PLOT_MARGIN + 10., PLOT_MARGIN + 15. * series as f32, colour, series_rollouts));
+        }
+
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+
+    pub fn write_csv<P:AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        return fs::write(path, self.to_csv());
+    }
+
+    pub fn write_svg<P:AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        return fs::write(path, self.to_svg());
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+
+#[test]
+fn test_scaling_experiment(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let budgets = [Budget::new(1, 1), Budget::new(10, 1), Budget::new(100, 1), Budget::new(10, 4)];
+    let config = ScalingConfig { games_per_budget: 60, threads: 4, seed: 0 };
+    let experiment = run_scaling(&empty, &budgets, Budget::new(10, 1), &config);
+
+    assert_eq!(experiment.points.len(), 4);
+    assert_eq!(Vec::from_iter(experiment.points.iter().map(|point| point.budget)), budgets);
+    assert!(experiment.points.iter().all(|point| point.result.games() == 60));
+    // more iterations are stronger
+    let elos = Vec::from_iter(experiment.points.iter().map(|point| point.result.elo().0));
+    assert!(elos[0] < 0.);
+    assert!(elos[2] > 0.);
+
+    let csv = experiment.to_csv();
+    let lines = Vec::from_iter(csv.lines());
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "iterations,rollouts,games,wins,draws,losses,win_rate,score,elo,elo_low,elo_high");
+    assert!(lines[1].starts_with("1,1,60,"));
+    assert!(lines[4].starts_with("10,4,60,"));
+
+    let svg = experiment.to_svg();
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<polyline").count(), 2);
+    assert_eq!(svg.matches("<circle").count(), 4);
+    assert!(svg.contains("Elo against mcts 10x1"));
+    assert!(!svg.contains("NaN") && !svg.contains("inf"));
+}
+
+#[test]
+fn test_scaling_plot_limits(){
+    // a single point that never loses has an infinite rating
+    let point = ScalingPoint { budget: Budget::new(5, 1), result: PairResult { first: 1, second: 0, wins: 10, draws: 0, losses: 0, sprt: None } };
+    let experiment = ScalingExperiment { reference: Budget::new(1, 1), points: vec![point] };
+
+    assert_eq!(experiment.to_csv().lines().nth(1).unwrap(), "5,1,10,10,0,0,1.0000,1.0000,inf,inf,inf");
+    let svg = experiment.to_svg();
+    assert_eq!(svg.matches("<circle").count(), 1);
+    assert!(!svg.contains("NaN") && !svg.contains("inf"));
+}
@@ -0,0 +1,556 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use super::mcts::GeneralGame;
+use super::connect4::Connect4;
+
+const WIDTH : usize = 6;
+const HEIGHT : usize = 6;
+const CELLS : usize = WIDTH * HEIGHT;
+// lowest score possible, a loss against the opponent's fourth stone
+const MIN_SCORE : i32 = -(CELLS as i32) / 2 + 3;
+
+// columns ordered from the centre outwards, central moves tend to be better
+const COLUMN_ORDER : [usize; WIDTH] = [2, 3, 1, 4, 0, 5];
+
+fn bottom_mask_col(col: usize) -> u64 {
+    return 1u64 << (col * (HEIGHT + 1));
+}
+
+fn top_mask_col(col: usize) -> u64 {
+    return 1u64 << (HEIGHT - 1 + col * (HEIGHT + 1));
+}
+
+fn column_mask(col: usize) -> u64 {
+    return ((1u64 << HEIGHT) - 1) << (col * (HEIGHT + 1));
+}
+
+// one bit at the bottom of every column
+fn bottom_mask() -> u64 {
+    return (0..WIDTH).fold(0u64, |mask, col| mask | bottom_mask_col(col));
+}
+
+fn board_mask() -> u64 {
+    return bottom_mask() * ((1u64 << HEIGHT) - 1);
+}
+
+// Connect4 as two bitboards: every column takes HEIGHT + 1 bits starting from the bottom,
+// the extra bit keeps lines from wrapping into the next column.
+// `position` holds the stones of the player to move, `mask` all stones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitBoard {
+    pub position: u64,
+    pub mask: u64,
+    pub moves: usize
+}
+
+impl BitBoard {
+    pub fn from_connect4(connect4: &Connect4) -> BitBoard {
+        let player = connect4.current_player();
+        let mut bitboard = BitBoard { position: 0, mask: 0, moves: 0 };
+
+        for row in 0..HEIGHT {
+            for col in 0..WIDTH {
+                let cell = connect4.board[row][col];
+                if cell == 0 {
+                    continue;
+                }
+                let bit = 1u64 << (col * (HEIGHT + 1) + HEIGHT - 1 - row);
+                bitboard.mask |= bit;
+                bitboard.moves += 1;
+                if cell == player {
+                    bitboard.position |= bit;
+                }
+            }
+        }
+
+        return bitboard;
+    }
+
+    pub fn can_play(&self, col: usize) -> bool {
+        return self.mask & top_mask_col(col) == 0;
+    }
+
+    pub fn play(&mut self, col: usize) {
+        self.position ^= self.mask;
+        self.mask |= self.mask + bottom_mask_col(col);
+        self.moves += 1;
+    }
+
+    // unique for every position
+    pub fn key(&self) -> u64 {
+        return self.position + self.mask;
+    }
+
+    // the key of the mirrored position, positions and their mirror image share the value
+    pub fn mirrored_key(&self) -> u64 {
+        let key = self.key();
+        let mut mirrored = 0u64;
+        for col in 0..WIDTH {
+            let bits = (key >> (col * (HEIGHT + 1))) & ((1u64 << (HEIGHT + 1)) - 1);
+            mirrored |= bits << ((WIDTH - 1 - col) * (HEIGHT + 1));
+        }
+        return mirrored;
+    }
+
+    pub fn is_winning_move(&self, col: usize) -> bool {
+        return self.winning_position() & self.possible() & column_mask(col) != 0;
+    }
+
+    pub fn can_win_next(&self) -> bool {
+        return self.winning_position() & self.possible() != 0;
+    }
+
+    // true if the player who just moved has four in a row
+    pub fn opponent_won(&self) -> bool {
+        return alignment(self.position ^ self.mask);
+    }
+
+    fn possible(&self) -> u64 {
+        return (self.mask + bottom_mask()) & board_mask();
+    }
+
+    fn winning_position(&self) -> u64 {
+        return winning_cells(self.position, self.mask);
+    }
+
+    fn opponent_winning_position(&self) -> u64 {
+        return winning_cells(self.position ^ self.mask, self.mask);
+    }
+
+    // moves that do not let the opponent win directly, assumes the player to move cannot win directly
+    fn possible_non_losing_moves(&self) -> u64 {
+        let mut possible = self.possible();
+        let opponent_win = self.opponent_winning_position();
+        let forced = possible & opponent_win;
+
+        if forced != 0 {
+            // two threats at once cannot be blocked
+            if forced & (forced - 1) != 0 {
+                return 0;
+            }
+            possible = forced;
+        }
+
+        // do not play below a cell where the opponent would win
+        return possible & !(opponent_win >> 1);
+    }
+
+    // number of cells where the player to move would win after playing `move_bit`, used for move ordering
+    fn move_score(&self, move_bit: u64) -> u32 {
+        return winning_cells(self.position | move_bit, self.mask).count_ones();
+    }
+}
+
+fn alignment(position: u64) -> bool {
+    for shift in [HEIGHT + 1, 1, HEIGHT, HEIGHT + 2] {
+        let pairs = position & (position >> shift);
+        if pairs & (pairs >> (2 * shift)) != 0 {
+            return true;
+        }
+    }
+    return false;
+}
+
+// empty cells that would complete four in a row for the stones in `position`
+fn winning_cells(position: u64, mask: u64) -> u64 {
+    // vertical
+    let mut cells = (position << 1) & (position << 2) & (position << 3);
+
+    for shift in [HEIGHT + 1, HEIGHT, HEIGHT + 2] {
+        let mut pairs = (position << shift) & (position << (2 * shift));
+        cells |= pairs & (position << (3 * shift));
+        cells |= pairs & (position >> shift);
+        pairs = (position >> shift) & (position >> (2 * shift));
+        cells |= pairs & (position << shift);
+        cells |= pairs & (position >> (3 * shift));
+    }
+
+    return cells & (board_mask() ^ mask);
+}
+
+// Exact result of a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Solution {
+    // 1 if the player to move wins, -1 if they lose, 0 for a draw
+    pub value: i8,
+    // plies until the game ends with perfect play, the winner hurries and the loser delays
+    pub plies: usize
+}
+
+impl Solution {
+    // converts a score of the solver for a position with `moves` stones
+    pub fn from_score(score: i32, moves: usize) -> Solution {
+        if score == 0 {
+            return Solution { value: 0, plies: CELLS - moves };
+        }
+
+        // the stone completing the line has the index `last`, counting from 1
+        let last = (CELLS as i32 + 2 - 2 * score.abs()) as usize;
+        // the winner's stones have the parity of the player to move (score > 0) or of the opponent
+        let parity = if score > 0 {(moves + 1) % 2} else {moves % 2};
+        let last = if last % 2 == parity {last} else {last - 1};
+        return Solution { value: score.signum() as i8, plies: last - moves };
+    }
+}
+
+// Transposition table storing an upper bound of the score, indexed by the key modulo the size.
+// Only the lower 32 bits of the key are stored, keys are below 2^(WIDTH*(HEIGHT+1)) so an odd
+// size above 2^(WIDTH*(HEIGHT+1)-32) still tells them apart by the Chinese remainder theorem.
+struct TranspositionTable {
+    keys: Vec<u32>,
+    values: Vec<i8>
+}
+
+impl TranspositionTable {
+    fn new(size: usize) -> TranspositionTable {
+        return TranspositionTable { keys: vec![0; size], values: vec![0; size] };
+    }
+
+    fn put(&mut self, key: u64, value: i8) {
+        let index = (key % self.keys.len() as u64) as usize;
+        self.keys[index] = key as u32;
+        self.values[index] = value;
+    }
+
+    fn get(&self, key: u64) -> Option<i8> {
+        let index = (key % self.keys.len() as u64) as usize;
+        if self.keys[index] == key as u32 && self.values[index] != 0 {
+            return Some(self.values[index]);
+        }
+        return None;
+    }
+}
+
+// Strong solver of the 6x6 `Connect4`, the empty board is a loss for the first player with the last stone.
+// Negamax with alpha-beta on bitboards, a transposition table,
+// null window iterations and an optional opening book of exact scores.
+// Scores follow the usual convention: a win with the k-th last stone of the winner scores k,
+// so positive scores are wins for the player to move, 0 is a draw.
+pub struct Connect4Solver {
+    table: TranspositionTable,
+    // exact scores keyed by `BitBoard::key`, both orientations of a position are stored
+    book: HashMap<u64, i8>,
+    pub nodes: u64
+}
+
+impl Connect4Solver {
+    // the default table takes about 40MB
+    pub fn new() -> Connect4Solver {
+        return Connect4Solver::with_table_size(8388593);
+    }
+
+    // `size` has to be odd and above 1024, a prime works best
+    pub fn with_table_size(size: usize) -> Connect4Solver {
+        assert!(size % 2 == 1 && size > 1024, "Invalid table size {}", size);
+        return Connect4Solver { table: TranspositionTable::new(size), book: HashMap::new(), nodes: 0 };
+    }
+
+    pub fn solve(&mut self, connect4: &Connect4) -> Solution {
+        let bitboard = BitBoard::from_connect4(connect4);
+        return Solution::from_score(self.score(connect4), bitboard.moves);
+    }
+
+    // exact score of the position for the player to move
+    pub fn score(&mut self, connect4: &Connect4) -> i32 {
+        let bitboard = BitBoard::from_connect4(connect4);
+
+        // the game is already over
+        if bitboard.opponent_won() {
+            return -((CELLS as i32 + 2 - bitboard.moves as i32) / 2);
+        }
+        if bitboard.moves == CELLS {
+            return 0;
+        }
+        return self.score_bitboard(&bitboard);
+    }
+
+    // score of playing every column, None for full columns
+    pub fn score_columns(&mut self, connect4: &Connect4) -> [Option<i32>; WIDTH] {
+        let bitboard = BitBoard::from_connect4(connect4);
+        let mut scores = [None; WIDTH];
+
+        if bitboard.opponent_won() {
+            return scores;
+        }
+
+        for (col, score) in scores.iter_mut().enumerate() {
+            if ! bitboard.can_play(col) {
+                continue;
+            }
+            if bitboard.is_winning_move(col) {
+                *score = Some((CELLS as i32 + 1 - bitboard.moves as i32) / 2);
+                continue;
+            }
+            let mut child = bitboard;
+            child.play(col);
+            *score = Some(if child.moves == CELLS {0} else {-self.score_bitboard(&child)});
+        }
+
+        return scores;
+    }
+
+    // Stores the exact score of every position up to `plies` moves after `root` in the book
+    pub fn build_book(&mut self, root: &Connect4, plies: usize) {
+        let mut positions = vec![BitBoard::from_connect4(root)];
+
+        for ply in 0..=plies {
+            let mut next = Vec::new();
+            for bitboard in positions {
+                if self.book.contains_key(&bitboard.key()) || bitboard.opponent_won() || bitboard.moves == CELLS {
+                    continue;
+                }
+
+                let score = self.score_bitboard(&bitboard);
+                self.book.insert(bitboard.key(), score as i8);
+                self.book.insert(bitboard.mirrored_key(), score as i8);
+
+                if ply < plies {
+                    for col in 0..WIDTH {
+                        if bitboard.can_play(col) {
+                            let mut child = bitboard;
+                            child.play(col);
+                            next.push(child);
+                        }
+                    }
+                }
+            }
+            positions = next;
+        }
+    }
+
+    pub fn book_len(&self) -> usize {
+        return self.book.len();
+    }
+
+    fn score_bitboard(&mut self, bitboard: &BitBoard) -> i32 {
+        if bitboard.can_win_next() {
+            return (CELLS as i32 + 1 - bitboard.moves as i32) / 2;
+        }
+        if let Some(&score) = self.book.get(&bitboard.key()) {
+            return score as i32;
+        }
+
+        // narrow the window around the score with null window searches
+        let mut min = -((CELLS - bitboard.moves) as i32) / 2;
+        let mut max = (CELLS as i32 + 1 - bitboard.moves as i32) / 2;
+        while min < max {
+            let mut middle = min + (max - min) / 2;
+            if middle <= 0 && min / 2 < middle {
+                middle = min / 2;
+            }
+            else if middle >= 0 && max / 2 > middle {
+                middle = max / 2;
+            }
+
+            let score = self.negamax(bitboard, middle, middle + 1);
+            if score <= middle {
+                max = score;
+            }
+            else {
+                min = score;
+            }
+        }
+        return min;
+    }
+
+    // assumes the player to move cannot win directly
+    fn negamax(&mut self, bitboard: &BitBoard, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+
+        let next = bitboard.possible_non_losing_moves();
+        if next == 0 {
+            return -((CELLS - bitboard.moves) as i32) / 2;
+        }
+        if bitboard.moves >= CELLS - 2 {
+            return 0;
+        }
+
+        if let Some(&score) = self.book.get(&bitboard.key()) {
+            return score as i32;
+        }
+
+        // the opponent cannot win with their next stone
+        let min = -((CELLS - 2 - bitboard.moves) as i32) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        // we cannot win with our next stone
+        let mut max = (CELLS as i32 - 1 - bitboard.moves as i32) / 2;
+        if let Some(value) = self.table.get(bitboard.key()) {
+            max = value as i32 + MIN_SCORE - 1;
+        }
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        // the moves creating the most threats first, ties keep the centre-first order
+        let mut moves = Vec::with_capacity(WIDTH);
+        for col in COLUMN_ORDER {
+            let move_bit = next & column_mask(col);
+            if move_bit != 0 {
+                moves.push((bitboard.move_score(move_bit), col));
+            }
+        }
+        moves.sort_by_key(|&(score, _)| Reverse(score));
+
+        for (_, col) in moves {
+            let mut child = *bitboard;
+            child.play(col);
+            let score = -self.negamax(&child, -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        self.table.put(bitboard.key(), (alpha - MIN_SCORE + 1) as i8);
+        return alpha;
+    }
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+#[cfg(test)]
+use super::negamax::{Negamax, WIN_VALUE};
+#[cfg(test)]
+use super::mcts::Symmetries;
+
+// negamax finds the fastest win and the slowest loss as well
+#[cfg(test)]
+fn negamax_solution(connect4: &Connect4) -> Solution {
+    let result = Negamax::new(None).search(connect4, CELLS);
+    if result.value == 0. {
+        let moves = connect4.board.iter().flatten().filter(|&&cell| cell != 0).count();
+        return Solution { value: 0, plies: CELLS - moves };
+    }
+    return Solution { value: result.value.signum() as i8, plies: (WIN_VALUE - result.value.abs()) as usize };
+}
+
+#[test]
+fn test_solver_bitboard(){
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..50 {
+        let mut connect4 = Connect4::empty();
+        let mut bitboard = BitBoard::from_connect4(&connect4);
+
+        while connect4.get_score() == 0 && !connect4.get_available().is_empty() {
+            let available = connect4.get_available();
+            assert_eq!(available, Vec::from_iter((0..WIDTH).filter(|&col| bitboard.can_play(col))));
+
+            let col = *available.choose(&mut rng).unwrap();
+            let mut next = connect4.clone();
+            next.update(col);
+            assert_eq!(bitboard.is_winning_move(col), next.get_score() != 0);
+
+            bitboard.play(col);
+            connect4 = next;
+            assert_eq!(bitboard, BitBoard::from_connect4(&connect4));
+            assert_eq!(bitboard.opponent_won(), connect4.get_score() != 0);
+
+            let mirrored = BitBoard::from_connect4(&connect4.transform(1));
+            assert_eq!(bitboard.mirrored_key(), mirrored.key());
+        }
+    }
+}
+
+#[test]
+fn test_solver_known_positions(){
+    let mut solver = Connect4Solver::with_table_size(1048573);
+
+    // O completes the bottom row
+    let connect4 = Connect4::from_string("X.....\nO.....\nX..O..\nO..X..\nXO.XX.\nXX.OOO\n").unwrap();
+    assert_eq!(solver.solve(&connect4), Solution { value: 1, plies: 1 });
+    assert_eq!(solver.score(&connect4), 11);
+    assert_eq!(solver.score_columns(&connect4)[2], Some(11));
+
+    // X has three in the bottom row with both ends open, O to move cannot block both
+    let connect4 = Connect4::from_string("......\n......\n......\n......\n.OO...\n.XXX..\n").unwrap();
+    assert_eq!(solver.solve(&connect4), Solution { value: -1, plies: 2 });
+    assert_eq!(solver.score(&connect4), -15);
+
+    // X has already won
+    let connect4 = Connect4::from_string("X.....\nX.....\nX..O..\nX..X..\nXO.XX.\nXX.OOO\n").unwrap();
+    assert_eq!(solver.solve(&connect4), Solution { value: -1, plies: 0 });
+    assert_eq!(solver.score_columns(&connect4), [None; WIDTH]);
+}
+
+#[test]
+fn test_solver_against_negamax(){
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut solver = Connect4Solver::with_table_size(1048573);
+
+    let mut solved = 0;
+    while solved < 30 {
+        let mut connect4 = Connect4::empty();
+        for _ in 0..22 {
+            if connect4.get_score() == 0 && !connect4.get_available().is_empty() {
+                connect4.update(*connect4.get_available().choose(&mut rng).unwrap());
+            }
+        }
+        if connect4.get_score() != 0 || connect4.get_available().is_empty() {
+            continue;
+        }
+
+        let solution = solver.solve(&connect4);
+        assert_eq!(solution, negamax_solution(&connect4), "{}", connect4);
+
+        // the best column keeps the score, all others are no better
+        let score = solver.score(&connect4);
+        let columns = solver.score_columns(&connect4);
+        assert_eq!(columns.iter().flatten().max(), Some(&score));
+        for (col, column) in columns.iter().enumerate() {
+            assert_eq!(column.is_some(), connect4.get_available().contains(&col));
+        }
+        solved += 1;
+    }
+}
+
+#[test]
+fn test_solver_book(){
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut connect4 = Connect4::empty();
+    while connect4.board.iter().flatten().filter(|&&cell| cell != 0).count() < 18 {
+        connect4.update(*connect4.get_available().choose(&mut rng).unwrap());
+        if connect4.get_score() != 0 {
+            connect4 = Connect4::empty();
+        }
+    }
+
+    let mut solver = Connect4Solver::with_table_size(1048573);
+    let expected = solver.score_columns(&connect4);
+
+    let mut solver = Connect4Solver::with_table_size(1048573);
+    solver.build_book(&connect4, 2);
+    assert!(solver.book_len() > 6);
+    let nodes = solver.nodes;
+    assert_eq!(solver.score_columns(&connect4), expected);
+    // the answers come from the book
+    assert_eq!(solver.nodes, nodes);
+
+    // mirrored positions are in the book as well
+    let mirrored = connect4.transform(1);
+    let mut expected_mirrored = expected;
+    expected_mirrored.reverse();
+    assert_eq!(solver.score_columns(&mirrored), expected_mirrored);
+    assert_eq!(solver.nodes, nodes);
+}
+
+// takes a while without optimisations
+#[test]
+#[ignore]
+fn test_solver_empty_board(){
+    let mut solver = Connect4Solver::new();
+    assert_eq!(solver.solve(&Connect4::empty()), Solution { value: -1, plies: 36 });
+    assert_eq!(solver.score_columns(&Connect4::empty()), [Some(-2), Some(-2), Some(-1), Some(-1), Some(-2), Some(-2)]);
+}
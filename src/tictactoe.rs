@@ -1,125 +1,33 @@
-use std::fmt;
-use super::mcts::GeneralGame;
+pub use super::mnk::MNKGame;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct TicTacToe{
-    pub board: [[i8; 3]; 3]
-}
-
-impl TicTacToe {
-    pub fn get_score(&self) -> i8 {
-        for target in [-1i8, 1i8]{
-            // check rows and columns
-            for i in 0..3usize{
-                if (0..3usize).all(|j| self.board[i][j]==target) {
-                    return target;
-                }
-                if (0..3usize).all(|j| self.board[j][i]==target) {
-                    return target;
-                }
-            }
-
-            // check diagonals
-            if (0..3usize).all(|i| self.board[i][i]==target) {
-                return target;
-            }
-            if (0..3usize).all(|i| self.board[i][2-i]==target) {
-                return target;
-            }
-        }
-
-        return 0;
-    }
-
-    pub fn get_available(&self) -> Vec<(usize, usize)> {
-        let mut res : Vec<(usize, usize)> = Vec::new();
-        for i in 0..3usize{
-            for j in 0..3usize{
-                if self.board[i][j] == 0{
-                    res.push((i,j));
-                }
-            }
-        }
-
-        return res;
-    }
-
-    pub fn from_string(val : &str) -> Option<TicTacToe> {
-        let mut tictactoe = TicTacToe {board:[[0;3];3]};
-
-        for (i,s) in val.chars().enumerate(){
-            if (i+1)%4==0 {
-                if s != '\n' && s != '\r'{
-                    return None;
-                }
-            }
-            else {
-                if s != '.'{
-                    let target = match s {
-                        'X' => 1i8,
-                        'O' => -1i8,
-                        _ => return None
-                    };
-                    let x = i%4;
-                    let y = i/4;
-                    tictactoe.board[y][x] = target;
-                }
-            }
-        }
+// three in a row on a 3x3 board, X starts
+pub type TicTacToe = MNKGame<3, 3, 3>;
 
-        return Some(tictactoe)
-    }
-
-    pub fn update(&mut self, indeces : (usize, usize), player: i8) {
-        self.board[indeces.0][indeces.1] = player;
-    }
-}
-
-impl GeneralGame for TicTacToe {
-    fn get_score(&self) -> i8 {
-        return self.get_score();
-    }
-    fn update(&mut self, index:usize, player:i8) {
-        self.update((index/3, index%3), player);
-    }
-
-    fn get_available(&self) -> Vec<usize> {
-        return Vec::from_iter(self.get_available().iter().map(|(i,j)| i*3+j));
-    }
-}
-
-impl fmt::Display for TicTacToe {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..3usize{
-            for j in 0..3usize{
-                write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
-            }
-            write!(f, "\n").unwrap();
-        }
-        write!(f, "")
-    }
-}
+#[cfg(test)]
+use super::mcts::{Reversible, Symmetries};
+#[cfg(test)]
+use rand::seq::SliceRandom;
 
 #[test]
 fn test_tictactoe_score() {
     let mut tictactoe: TicTacToe;
 
-    tictactoe = TicTacToe {board: [[0,0,0],[0,0,0],[0,0,0]]};
+    tictactoe = TicTacToe::from_board([[0,0,0],[0,0,0],[0,0,0]]);
     assert_eq!(tictactoe.get_score(), 0);
 
-    tictactoe = TicTacToe {board: [[1,0,0],[1,0,0],[1,0,0]]};
+    tictactoe = TicTacToe::from_board([[1,0,0],[1,0,0],[1,0,0]]);
     assert_eq!(tictactoe.get_score(), 1);
 
-    tictactoe = TicTacToe {board: [[0,-1,0],[0,-1,0],[0,-1,0]]};
+    tictactoe = TicTacToe::from_board([[0,-1,0],[0,-1,0],[0,-1,0]]);
     assert_eq!(tictactoe.get_score(), -1);
 
-    tictactoe = TicTacToe {board: [[-1,0,0],[0,-1,0],[0,0,-1]]};
+    tictactoe = TicTacToe::from_board([[-1,0,0],[0,-1,0],[0,0,-1]]);
     assert_eq!(tictactoe.get_score(), -1);
 
-    tictactoe = TicTacToe {board: [[-1,0,1],[0,1,0],[1,0,-1]]};
+    tictactoe = TicTacToe::from_board([[-1,0,1],[0,1,0],[1,0,-1]]);
     assert_eq!(tictactoe.get_score(), 1);
 
-    tictactoe = TicTacToe {board: [[-1,0,1],[0,0,1],[1,0,1]]};
+    tictactoe = TicTacToe::from_board([[-1,0,1],[0,0,1],[1,0,1]]);
     assert_eq!(tictactoe.get_score(), 1);
 }
 
@@ -127,16 +35,16 @@ fn test_tictactoe_score() {
 fn test_tictactoe_available() {
     let mut tictactoe: TicTacToe;
 
-    tictactoe = TicTacToe {board: [[0,0,0],[0,-1,0],[1,0,-1]]};
+    tictactoe = TicTacToe::from_board([[0,0,0],[0,-1,0],[1,0,-1]]);
     assert_eq!(tictactoe.get_available(), [(0,0),(0,1),(0,2),(1,0),(1,2),(2,1)]);
 
-    tictactoe = TicTacToe {board: [[-1,1,0],[-1,-1,-1],[1,1,0]]};
+    tictactoe = TicTacToe::from_board([[-1,1,0],[-1,-1,-1],[1,1,0]]);
     assert_eq!(tictactoe.get_available(), [(0,2),(2,2)]);
 }
 
 #[test]
 fn test_tictactoe_fmt(){
-    let tictactoe = TicTacToe {board: [[1,1,-1],[0,0,-1],[1,0,0]]};
+    let tictactoe = TicTacToe::from_board([[1,1,-1],[0,0,-1],[1,0,0]]);
     let tictactoe_str = format!("{}", tictactoe);
     assert_eq!(tictactoe_str, "X X O \n. . O \nX . . \n");
 }
@@ -146,8 +54,138 @@ fn test_tictactoe_from_string(){
     let mut tictactoe;
 
     tictactoe = TicTacToe::from_string("XX.\nO.O\n..X\r");
-    assert_eq!(tictactoe, Some(TicTacToe {board: [[1,1,0],[-1,0,-1],[0,0,1]]}));
+    assert_eq!(tictactoe, Some(TicTacToe::from_board([[1,1,0],[-1,0,-1],[0,0,1]])));
 
     tictactoe = TicTacToe::from_string("XX.\rO.O\n...X\n");
     assert_eq!(tictactoe, None);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tictactoe_undo(){
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..100 {
+        let mut tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
+        let mut history = Vec::new();
+
+        while tictactoe.get_score() == 0 && !tictactoe.get_available().is_empty() {
+            let (row, col) = *tictactoe.get_available().choose(&mut rng).unwrap();
+            let before = tictactoe.clone();
+            let player = tictactoe.current_player();
+            let undo = tictactoe.update_with_undo((row, col));
+            assert_eq!(tictactoe.board[row][col], player);
+            assert_eq!(tictactoe.current_player(), -player);
+
+            history.push((before, undo));
+        }
+
+        while let Some((before, undo)) = history.pop() {
+            tictactoe.undo(undo);
+            assert_eq!(tictactoe, before);
+        }
+        assert_eq!(tictactoe, TicTacToe::from_string("...\n...\n...").unwrap());
+    }
+}
+
+#[test]
+fn test_tictactoe_current_player(){
+    let mut tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
+    assert_eq!(tictactoe.current_player(), 1);
+
+    tictactoe.update((1, 1));
+    assert_eq!(tictactoe, TicTacToe::from_string("...\n.X.\n...").unwrap());
+    assert_eq!(tictactoe.current_player(), -1);
+
+    tictactoe.update((0, 0));
+    assert_eq!(tictactoe, TicTacToe::from_string("O..\n.X.\n...").unwrap());
+    assert_eq!(tictactoe.current_player(), 1);
+}
+
+// every position that can occur in a game, finished games are not continued
+#[cfg(test)]
+fn all_positions() -> Vec<TicTacToe> {
+    let mut positions = vec![TicTacToe::from_board([[0; 3]; 3])];
+    let mut next = 0usize;
+    while next < positions.len() {
+        let current = positions[next].clone();
+        next += 1;
+        if current.get_score() != 0 {
+            continue;
+        }
+        for index in current.get_available() {
+            let mut child = current.clone();
+            child.update(index);
+            if ! positions.contains(&child) {
+                positions.push(child);
+            }
+        }
+    }
+    return positions;
+}
+
+#[test]
+fn test_tictactoe_incremental_score(){
+    // the score kept up to date by `update` against a scan of the whole board
+    for tictactoe in all_positions() {
+        assert_eq!(tictactoe, TicTacToe::from_board(tictactoe.board));
+        if tictactoe.get_score() != 0 {
+            continue;
+        }
+        for index in tictactoe.get_available() {
+            let mut child = tictactoe.clone();
+            child.update(index);
+            assert_eq!(child.get_score(), TicTacToe::from_board(child.board).get_score(), "{}", child);
+        }
+    }
+}
+
+#[test]
+fn test_tictactoe_symmetries(){
+    let positions = all_positions();
+    assert_eq!(positions.len(), 5478);
+
+    for tictactoe in positions.iter() {
+        assert_eq!(&tictactoe.transform(0), tictactoe);
+        let (key, _) = tictactoe.canonical_key();
+
+        let mut transformed_boards = Vec::new();
+        for symmetry in 0..8 {
+            let transformed = tictactoe.transform(symmetry);
+            assert_eq!(&transformed.transform(tictactoe.inverse(symmetry)), tictactoe);
+            assert_eq!(transformed.get_score(), tictactoe.get_score());
+            assert_eq!(transformed.current_player(), tictactoe.current_player());
+            assert_eq!(transformed.canonical_key().0, key);
+
+            let (canonical, canonical_symmetry) = tictactoe.canonical_key();
+            assert_eq!(tictactoe.transform(canonical_symmetry).key(), canonical);
+
+            let mut available = Vec::from_iter(tictactoe.get_available().iter().map(|index| tictactoe.transform_move(symmetry, index)));
+            available.sort();
+            assert_eq!(available, transformed.get_available());
+
+            for index in tictactoe.get_available() {
+                let mapped = tictactoe.transform_move(symmetry, &index);
+                assert_eq!(transformed.transform_move(tictactoe.inverse(symmetry), &mapped), index);
+
+                let (mut played, mut played_transformed) = (tictactoe.clone(), transformed.clone());
+                played.update(index);
+                played_transformed.update(mapped);
+                assert_eq!(played.transform(symmetry), played_transformed);
+            }
+            transformed_boards.push(transformed.board);
+        }
+
+        // the symmetries are distinct transformations
+        if tictactoe.board == [[1, 0, 0], [0, 0, 0], [-1, 1, 0]] {
+            transformed_boards.sort();
+            transformed_boards.dedup();
+            assert_eq!(transformed_boards.len(), 8);
+        }
+    }
+
+    // the number of positions up to symmetry
+    let mut keys = Vec::from_iter(positions.iter().map(|tictactoe| tictactoe.canonical_key().0));
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), 765);
+}
@@ -0,0 +1,368 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::mcts::GeneralGame;
+use super::agent::{Agent, play_game};
+
+// A participant of a tournament. Agents are created inside the worker threads,
+// `make` gets a seed that is different for every game.
+pub struct Entrant<T:GeneralGame> {
+    pub name: String,
+    pub make: Box<dyn Fn(u64) -> Box<dyn Agent<T>> + Send + Sync>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    // every entrant plays every other
+    RoundRobin,
+    // the first entrant plays all others
+    Gauntlet
+}
+
+// Sequential probability ratio test of H0: elo = elo0 against H1: elo = elo1 for the first player of a pairing,
+// `alpha` and `beta` are the probabilities of accepting H1 and H0 wrongly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f32,
+    pub elo1: f32,
+    pub alpha: f32,
+    pub beta: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtResult {
+    AcceptH0,
+    AcceptH1,
+    Continue
+}
+
+impl Sprt {
+    // bounds of the log likelihood ratio
+    pub fn bounds(&self) -> (f32, f32) {
+        return ((self.beta / (1. - self.alpha)).ln(), ((1. - self.beta) / self.alpha).ln());
+    }
+
+    // Log likelihood ratio for a normal approximation of the game scores
+    pub fn llr(&self, wins: u64, draws: u64, losses: u64) -> f32 {
+        let games = (wins + draws + losses) as f32;
+        let (w, d, l) = (wins as f32 / games, draws as f32 / games, losses as f32 / games);
+        let mean = w + d / 2.;
+        let variance = w * (1. - mean).powi(2) + d * (0.5 - mean).powi(2) + l * mean.powi(2);
+        if games == 0. || variance == 0. {
+            // all games ended the same way, nothing to estimate the spread from
+            return 0.;
+        }
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+
+        return games * (s1 - s0) * (2. * mean - s0 - s1) / (2. * variance);
+    }
+
+    pub fn test(&self, wins: u64, draws: u64, losses: u64) -> SprtResult {
+        let llr = self.llr(wins, draws, losses);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            return SprtResult::AcceptH1;
+        }
+        if llr <= lower {
+            return SprtResult::AcceptH0;
+        }
+        return SprtResult::Continue;
+    }
+}
+
+pub fn expected_score(elo: f32) -> f32 {
+    return 1. / (1. + 10f32.powf(-elo / 400.));
+}
+
+// Elo difference giving the expected score `score`, infinite for 0 and 1
+pub fn elo_from_score(score: f32) -> f32 {
+    return -400. * (1. / score - 1.).log10();
+}
+
+pub struct TournamentConfig {
+    pub schedule: Schedule,
+    // games of every pairing, the colours alternate
+    pub games_per_pair: usize,
+    pub threads: usize,
+    // stops pairings early once the test is decided
+    pub sprt: Option<Sprt>,
+    pub seed: u64
+}
+
+impl Default for TournamentConfig {
+    fn default() -> TournamentConfig {
+        return TournamentConfig { schedule: Schedule::RoundRobin, games_per_pair: 100, threads: 4, sprt: None, seed: 0 };
+    }
+}
+
+// Results of a pairing, counted for `first`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairResult {
+    pub first: usize,
+    pub second: usize,
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+    pub sprt: Option<SprtResult>
+}
+
+impl PairResult {
+    pub fn games(&self) -> u64 {
+        return self.wins + self.draws + self.losses;
+    }
+
+    pub fn score(&self) -> f32 {
+        return (self.wins as f32 + self.draws as f32 / 2.) / self.games() as f32;
+    }
+
+    // Elo difference of `first` over `second` with the bounds of the 95% confidence interval
+    pub fn elo(&self) -> (f32, f32, f32) {
+        let games = self.games() as f32;
+        let score = self.score();
+        let (w, d, l) = (self.wins as f32 / games, self.draws as f32 / games, self.losses as f32 / games);
+        let variance = w * (1. - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2);
+        let margin = 1.96 * (variance / games).sqrt();
+
+        let low = elo_from_score((score - margin).max(0.));
+        let high = elo_from_score((score + margin).min(1.));
+        return (elo_from_score(score), low, high);
+    }
+
+    // the same results counted for `second`, a decided SPRT no longer applies
+    pub fn swapped(&self) -> PairResult {
+        return PairResult { first: self.second, second: self.first, wins: self.losses, draws: self.draws, losses: self.wins, sprt: None };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentResult {
+    pub names: Vec<String>,
+    pub pairs: Vec<PairResult>
+}
+
+fn sprt_name(sprt: Option<SprtResult>) -> &'static str {
+    return match sprt {
+        Some(SprtResult::AcceptH0) => "H0",
+        Some(SprtResult::AcceptH1) => "H1",
+        Some(SprtResult::Continue) => "continue",
+        None => ""
+    };
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for s in value.chars() {
+        match s {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            s if (s as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", s as u32)),
+            s => escaped.push(s)
+        }
+    }
+    escaped.push('"');
+    return escaped;
+}
+
+// JSON has no infinity
+fn json_number(value: f32) -> String {
+    return if value.is_finite() {format!("{:.1}", value)} else {String::from("null")};
+}
+
+impl TournamentResult {
+    // one line per pairing
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("first,second,games,wins,draws,losses,score,elo,elo_low,elo_high,sprt\n");
+        for pair in self.pairs.iter() {
+            let (elo, low, high) = pair.elo();
+            csv.push_str(&format!("{},{},{},{},{},{},{:.4},{:.1},{:.1},{:.1},{}\n",
+                self.names[pair.first].replace(',', ";"), self.names[pair.second].replace(',', ";"),
+                pair.games(), pair.wins, pair.draws, pair.losses, pair.score(), elo, low, high, sprt_name(pair.sprt)));
+        }
+        return csv;
+    }
+
+    pub fn to_json(&self) -> String {
+        let pairs = Vec::from_iter(self.pairs.iter().map(|pair| {
+            let (elo, low, high) = pair.elo();
+            format!("{{\"first\":{},\"second\":{},\"wins\":{},\"draws\":{},\"losses\":{},\"elo\":{},\"elo_low\":{},\"elo_high\":{},\"sprt\":{}}}",
+                json_string(&self.names[pair.first]), json_string(&self.names[pair.second]),
+                pair.wins, pair.draws, pair.losses, json_number(elo), json_number(low), json_number(high),
+                if pair.sprt.is_some() {json_string(sprt_name(pair.sprt))} else {String::from("null")})
+        }));
+        let names = Vec::from_iter(self.names.iter().map(|name| json_string(name)));
+        return format!("{{\"entrants\":[{}],\"pairs\":[{}]}}\n", names.join(","), pairs.join(","));
+    }
+
+    pub fn write_csv<P:AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        return fs::write(path, self.to_csv());
+    }
+
+    pub fn write_json<P:AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        return fs::write(path, self.to_json());
+    }
+}
+
+// Plays all pairings of the schedule from `start` on `config.threads` threads
+pub fn run_tournament<T:GeneralGame + Sync>(start: &T, entrants: &[Entrant<T>], config: &TournamentConfig) -> TournamentResult {
+    let mut pairs = Vec::new();
+    for first in 0..entrants.len() {
+        for second in first+1..entrants.len() {
+            if config.schedule == Schedule::Gauntlet && first != 0 {
+                continue;
+            }
+            pairs.push(PairResult { first, second, wins: 0, draws: 0, losses: 0, sprt: config.sprt.map(|_| SprtResult::Continue) });
+        }
+    }
+
+    // every job is one game, the pairings are interleaved so that early stopping saves work
+    let jobs = Vec::from_iter((0..config.games_per_pair).flat_map(|game| (0..pairs.len()).map(move |pair| (pair, game))));
+    let next_job = AtomicUsize::new(0);
+    let pairs = Mutex::new(pairs);
+
+    std::thread::scope(|scope| {
+        for _ in 0..config.threads.max(1) {
+            scope.spawn(|| loop {
+                let job = next_job.fetch_add(1, Ordering::SeqCst);
+                if job >= jobs.len() {
+                    break;
+                }
+                let (pair, game) = jobs[job];
+
+                let (first, second) = {
+                    let pairs = pairs.lock().unwrap();
+                    if pairs[pair].sprt.is_some() && pairs[pair].sprt != Some(SprtResult::Continue) {
+                        continue;
+                    }
+                    (pairs[pair].first, pairs[pair].second)
+                };
+
+                let seed = config.seed.wrapping_mul(1000003).wrapping_add(job as u64 * 2);
+                let mut first_agent = (entrants[first].make)(seed);
+                let mut second_agent = (entrants[second].make)(seed + 1);
+
+                // the colours alternate, the result is counted for `first`
+                let result = if game % 2 == 0 {
+                    play_game(start, first_agent.as_mut(), second_agent.as_mut()).expect("Illegal move in a tournament game").result
+                }
+                else {
+                    -play_game(start, second_agent.as_mut(), first_agent.as_mut()).expect("Illegal move in a tournament game").result
+                };
+
+                let mut pairs = pairs.lock().unwrap();
+                let pair = &mut pairs[pair];
+                match result {
+                    1 => pair.wins += 1,
+                    -1 => pair.losses += 1,
+                    _ => pair.draws += 1
+                }
+                if let (Some(sprt), Some(SprtResult::Continue)) = (config.sprt, pair.sprt) {
+                    pair.sprt = Some(sprt.test(pair.wins, pair.draws, pair.losses));
+                }
+            });
+        }
+    });
+
+    return TournamentResult { names: Vec::from_iter(entrants.iter().map(|entrant| entrant.name.clone())), pairs: pairs.into_inner().unwrap() };
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::agent::{RandomAgent, AlphaBetaAgent, MctsAgent};
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+
+#[test]
+fn test_tournament_elo(){
+    assert_eq!(elo_from_score(0.5), 0.);
+    assert!((elo_from_score(0.75) - 190.85).abs() < 0.01);
+    assert!((expected_score(elo_from_score(0.3)) - 0.3).abs() < 0.0001);
+    assert_eq!(elo_from_score(1.), f32::INFINITY);
+
+    let pair = PairResult { first: 0, second: 1, wins: 60, draws: 20, losses: 20, sprt: None };
+    assert_eq!(pair.games(), 100);
+    assert_eq!(pair.score(), 0.7);
+    let (elo, low, high) = pair.elo();
+    assert!((elo - 147.19).abs() < 0.01);
+    assert!(low < elo && elo < high);
+    assert!(low > 0.);
+
+    // more games, narrower interval
+    let more = PairResult { first: 0, second: 1, wins: 600, draws: 200, losses: 200, sprt: None };
+    let (_, more_low, more_high) = more.elo();
+    assert!(more_high - more_low < high - low);
+}
+
+#[test]
+fn test_tournament_sprt(){
+    let sprt = Sprt { elo0: 0., elo1: 50., alpha: 0.05, beta: 0.05 };
+    let (lower, upper) = sprt.bounds();
+    assert!((lower + 2.944).abs() < 0.001);
+    assert!((upper - 2.944).abs() < 0.001);
+
+    assert_eq!(sprt.test(10, 0, 10), SprtResult::Continue);
+    assert_eq!(sprt.test(0, 10, 0), SprtResult::Continue);
+    assert_eq!(sprt.test(150, 0, 50), SprtResult::AcceptH1);
+    assert_eq!(sprt.test(150, 0, 150), SprtResult::AcceptH0);
+    assert!(sprt.llr(60, 0, 40) > sprt.llr(55, 0, 45));
+}
+
+#[cfg(test)]
+fn tictactoe_entrants() -> Vec<Entrant<TicTacToe>> {
+    return vec![
+        Entrant { name: String::from("alphabeta"), make: Box::new(|_| Box::new(AlphaBetaAgent { depth: 9, evaluator: None })) },
+        Entrant { name: String::from("random"), make: Box::new(|seed| Box::new(RandomAgent { rng: StdRng::seed_from_u64(seed) })) },
+        Entrant { name: String::from("mcts, 200"), make: Box::new(|seed| Box::new(MctsAgent::new(200, 1, StdRng::seed_from_u64(seed)))) }
+    ];
+}
+
+#[test]
+fn test_tournament_round_robin(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let config = TournamentConfig { games_per_pair: 10, threads: 3, ..TournamentConfig::default() };
+    let result = run_tournament(&empty, &tictactoe_entrants(), &config);
+
+    assert_eq!(result.names, ["alphabeta", "random", "mcts, 200"]);
+    assert_eq!(Vec::from_iter(result.pairs.iter().map(|pair| (pair.first, pair.second))), [(0, 1), (0, 2), (1, 2)]);
+    for pair in result.pairs.iter() {
+        assert_eq!(pair.games(), 10);
+        assert_eq!(pair.sprt, None);
+    }
+    // perfect play never loses
+    assert_eq!(result.pairs[0].losses, 0);
+    assert_eq!(result.pairs[1].losses, 0);
+
+    let csv = result.to_csv();
+    let lines = Vec::from_iter(csv.lines());
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "first,second,games,wins,draws,losses,score,elo,elo_low,elo_high,sprt");
+    assert!(lines[3].starts_with("random,mcts; 200,10,"));
+
+    let json = result.to_json();
+    assert!(json.starts_with("{\"entrants\":[\"alphabeta\",\"random\",\"mcts, 200\"],\"pairs\":[{\"first\":\"alphabeta\",\"second\":\"random\","));
+    assert_eq!(json.matches("\"first\"").count(), 3);
+    assert_eq!(json_string("a\"b\\"), "\"a\\\"b\\\\\"");
+}
+
+#[test]
+fn test_tournament_gauntlet_sprt(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let sprt = Sprt { elo0: 0., elo1: 100., alpha: 0.05, beta: 0.05 };
+    let config = TournamentConfig { schedule: Schedule::Gauntlet, games_per_pair: 400, threads: 2, sprt: Some(sprt), seed: 1 };
+    let result = run_tournament(&empty, &tictactoe_entrants()[..2], &config);
+
+    assert_eq!(result.pairs.len(), 1);
+    let pair = &result.pairs[0];
+    assert_eq!(pair.sprt, Some(SprtResult::AcceptH1));
+    assert!(pair.games() < 400);
+
+    let path = std::env::temp_dir().join(format!("mcts-tournament-test-{}.csv", std::process::id()));
+    result.write_csv(&path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), result.to_csv());
+    fs::remove_file(&path).unwrap();
+}
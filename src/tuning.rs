@@ -0,0 +1,165 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use super::mcts::GeneralGame;
+use super::agent::Agent;
+use super::tournament::{Entrant, TournamentConfig, Schedule, PairResult, run_tournament};
+
+// A numeric search parameter, integer parameters are rounded by the agent factory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    // the perturbation of the first iteration, also the unit the learning rate is measured in
+    pub step: f64
+}
+
+impl Parameter {
+    pub fn new(name: &str, value: f64, min: f64, max: f64, step: f64) -> Parameter {
+        return Parameter { name: String::from(name), value, min, max, step };
+    }
+}
+
+// Simultaneous perturbation stochastic approximation, the gains follow Spall's recommendations
+pub struct SpsaConfig {
+    pub iterations: usize,
+    // games between the two perturbed parameter sets per iteration
+    pub games_per_iteration: usize,
+    // games between the tuned and the initial parameters at the end
+    pub final_games: usize,
+    pub learning_rate: f64,
+    pub alpha: f64,
+    pub gamma: f64,
+    pub threads: usize,
+    pub seed: u64
+}
+
+impl Default for SpsaConfig {
+    fn default() -> SpsaConfig {
+        return SpsaConfig { iterations: 100, games_per_iteration: 20, final_games: 200, learning_rate: 1., alpha: 0.602, gamma: 0.101, threads: 4, seed: 0 };
+    }
+}
+
+pub struct TuningResult {
+    pub initial: Vec<Parameter>,
+    pub tuned: Vec<Parameter>,
+    // the parameter values after every iteration
+    pub history: Vec<Vec<f64>>,
+    // the tuned parameters playing the initial ones
+    pub gain: PairResult
+}
+
+impl fmt::Display for TuningResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (initial, tuned) in self.initial.iter().zip(self.tuned.iter()) {
+            writeln!(f, "{}: {} -> {:.4}", tuned.name, initial.value, tuned.value)?;
+        }
+        let (elo, low, high) = self.gain.elo();
+        return write!(f, "Elo gain: {:.1} [{:.1}, {:.1}] in {} games (+{} ={} -{})",
+            elo, low, high, self.gain.games(), self.gain.wins, self.gain.draws, self.gain.losses);
+    }
+}
+
+fn with_values(parameters: &[Parameter], values: &[f64]) -> Vec<Parameter> {
+    return Vec::from_iter(parameters.iter().zip(values).map(|(parameter, &value)| Parameter { value, ..parameter.clone() }));
+}
+
+fn entrant<T:GeneralGame, F>(name: &str, values: Vec<f64>, make: &Arc<F>) -> Entrant<T>
+where F: Fn(&[f64], u64) -> Box<dyn Agent<T>> + Send + Sync + 'static {
+    let make = make.clone();
+    return Entrant { name: String::from(name), make: Box::new(move |seed| make(&values, seed)) };
+}
+
+// Tunes `parameters` by self-play from `start`, `make` creates an agent from parameter values and a seed
+pub fn spsa<T, F>(start: &T, parameters: &[Parameter], make: F, config: &SpsaConfig) -> TuningResult
+where T: GeneralGame + Sync, F: Fn(&[f64], u64) -> Box<dyn Agent<T>> + Send + Sync + 'static {
+    let make = Arc::new(make);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let stability = config.iterations as f64 / 10.;
+
+    let mut values = Vec::from_iter(parameters.iter().map(|parameter| parameter.value));
+    let mut history = Vec::new();
+
+    for iteration in 0..config.iterations {
+        let a = config.learning_rate / (stability + iteration as f64 + 1.).powf(config.alpha);
+        let c = 1. / (iteration as f64 + 1.).powf(config.gamma);
+        let delta = Vec::from_iter(parameters.iter().map(|_| if rng.gen::<bool>() {1.} else {-1.}));
+
+        // the perturbation is measured in steps of the parameter
+        let perturbed = |sign: f64| Vec::from_iter(parameters.iter().zip(values.iter()).zip(delta.iter())
+            .map(|((parameter, value), delta)| (value + sign * c * delta * parameter.step).clamp(parameter.min, parameter.max)));
+
+        let entrants = [entrant("plus", perturbed(1.), &make), entrant("minus", perturbed(-1.), &make)];
+        let tournament = TournamentConfig {
+            schedule: Schedule::Gauntlet, games_per_pair: config.games_per_iteration, threads: config.threads, sprt: None, seed: rng.gen()
+        };
+        let pair = &run_tournament(start, &entrants, &tournament).pairs[0];
+        let result = (pair.wins as f64 - pair.losses as f64) / pair.games().max(1) as f64;
+
+        for ((value, parameter), delta) in values.iter_mut().zip(parameters).zip(delta.iter()) {
+            *value = (*value + a * result / (2. * c * delta) * parameter.step).clamp(parameter.min, parameter.max);
+        }
+        history.push(values.clone());
+    }
+
+    let initial = Vec::from_iter(parameters.iter().map(|parameter| parameter.value));
+    let entrants = [entrant("tuned", values.clone(), &make), entrant("initial", initial, &make)];
+    let tournament = TournamentConfig {
+        schedule: Schedule::Gauntlet, games_per_pair: config.final_games, threads: config.threads, sprt: None, seed: rng.gen()
+    };
+    let gain = run_tournament(start, &entrants, &tournament).pairs.remove(0);
+
+    return TuningResult { initial: parameters.to_vec(), tuned: with_values(parameters, &values), history, gain };
+}
+
+
+#[cfg(test)]
+use super::agent::MctsAgent;
+#[cfg(test)]
+use super::tictactoe::TicTacToe;
+
+#[cfg(test)]
+fn mcts_agent(values: &[f64], seed: u64) -> Box<dyn Agent<TicTacToe>> {
+    let mut agent = MctsAgent::new(values[0].round() as usize, 1, StdRng::seed_from_u64(seed));
+    if values.len() > 1 {
+        agent.exploration = values[1] as f32;
+    }
+    return Box::new(agent);
+}
+
+#[test]
+fn test_spsa_iterations(){
+    // more iterations are stronger, the tuner should find that out
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let parameters = [Parameter::new("iterations", 4., 1., 60., 4.)];
+    let config = SpsaConfig { iterations: 30, games_per_iteration: 20, final_games: 100, learning_rate: 4., threads: 4, ..SpsaConfig::default() };
+    let result = spsa(&empty, &parameters, mcts_agent, &config);
+
+    assert_eq!(result.history.len(), 30);
+    assert_eq!(result.initial, parameters);
+    assert_eq!(result.tuned[0].name, "iterations");
+    assert!(result.tuned[0].value > 10., "{}", result);
+    assert!(result.history.iter().all(|values| values[0] >= 1. && values[0] <= 60.));
+
+    assert_eq!(result.gain.games(), 100);
+    assert!(result.gain.score() > 0.5, "{}", result);
+    assert!(result.to_string().starts_with("iterations: 4 -> "));
+}
+
+#[test]
+fn test_spsa_deterministic(){
+    let empty = TicTacToe::from_string("...\n...\n...").unwrap();
+    let parameters = [Parameter::new("iterations", 10., 1., 20., 2.), Parameter::new("exploration", 2.8, 0., 5., 0.5)];
+    let config = SpsaConfig { iterations: 3, games_per_iteration: 4, final_games: 4, threads: 2, seed: 5, ..SpsaConfig::default() };
+
+    let first = spsa(&empty, &parameters, mcts_agent, &config);
+    let second = spsa(&empty, &parameters, mcts_agent, &SpsaConfig { threads: 1, ..config });
+    assert_eq!(first.history, second.history);
+    assert_eq!(first.gain, second.gain);
+    assert_eq!(first.tuned.len(), 2);
+    assert_eq!(first.tuned[1].value, first.history[2][1]);
+}